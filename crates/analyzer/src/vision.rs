@@ -38,7 +38,7 @@ impl Labels {
 pub struct EyeballerRunner {
     _env: Arc<Environment>,
     session: Session,
-    input_name: String,
+    _input_name: String,
     labels: Labels,
 }
 
@@ -59,14 +59,14 @@ impl EyeballerRunner {
 
         let input_name = session
             .inputs
-            .get(0)
+            .first()
             .map(|i| i.name.clone())
             .unwrap_or_else(|| "input".to_string());
 
         Ok(Self {
             _env: env,
             session,
-            input_name,
+            _input_name: input_name,
             labels,
         })
     }
@@ -213,8 +213,8 @@ impl EyeballerRunner {
                     .unwrap_or_else(|| top_i.to_string()),
                 format!("{:.6}", top_p),
             ];
-            for j in 0..ncls {
-                row.push(format!("{:.6}", probs[j]));
+            for pv in probs.iter().take(ncls) {
+                row.push(format!("{:.6}", pv));
             }
             w.write_record(&row)?;
         }
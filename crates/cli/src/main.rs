@@ -1,9 +1,23 @@
 use anyhow::{Result, anyhow};
 use clap::{ArgAction, CommandFactory, Parser, Subcommand};
-use std::{fs, path::PathBuf};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
 use analyzer::vision::*;
 use server::server;
-use scanner::run_scan;
+use core::findings::{write_sarif, Finding, JsonlWriter};
+use core::rules::RuleSet;
+use scanner::{
+    run_scan_with, AlienVaultOtx, CdxFilter, CommonCrawl, Credentials, ImportedScope, LocalFile, RateLimit,
+    Redirects, ScanConfig, Scope, Snapshots, UrlScan, UrlSource, WaybackCdx, DEFAULT_MAX_PAGES,
+    reanalyze, scan_path, verify_scan, Fetcher, GitHubVerifier, LocalScanStats, SendGridVerifier,
+    SlackVerifier, StripeVerifier, Verifiers,
+    report::URL_STATUS_FILE,
+};
 
 #[derive(Subcommand, Debug)]
 enum Cmd {
@@ -16,6 +30,52 @@ enum Cmd {
         #[arg(long, value_name = "PORT", default_value_t = 8000)]
         port: u16,
     },
+    /// Импорт скоупа программы bug bounty (выгрузка HackerOne/Bugcrowd, CSV или JSON)
+    Import {
+        /// Файл выгрузки (.csv или .json)
+        #[arg(value_name = "FILE")]
+        file: PathBuf,
+        /// Сохранить правила в файл скоупа (формат --scope-file)
+        #[arg(long, value_name = "FILE")]
+        write_scope: Option<PathBuf>,
+        /// Запустить скан для каждого wildcard-домена из выгрузки; --scope и
+        /// --out-of-scope дополняют импортированный скоуп
+        #[arg(long, action = ArgAction::SetTrue)]
+        scan: bool,
+    },
+    /// Поиск секретов в локальных файлах и папках, без сети (`-` — stdin)
+    Secrets {
+        /// Файлы и папки; архивы распаковываются
+        #[arg(value_name = "PATH", required = true)]
+        paths: Vec<PathBuf>,
+        /// Записать находки JSONL в файл (по умолчанию — в stdout)
+        #[arg(long, value_name = "FILE")]
+        jsonl: Option<PathBuf>,
+        /// Записать находки в SARIF 2.1.0
+        #[arg(long, value_name = "FILE")]
+        sarif: Option<PathBuf>,
+    },
+    /// Заново проверить файлы прошлого скана текущими правилами, без загрузки
+    Reanalyze {
+        /// Папка скана (DOMAIN с assets/ и JSscripts/)
+        #[arg(value_name = "SCAN_DIR")]
+        dir: PathBuf,
+    },
+    /// Правила поиска секретов (встроенные + --rules): список, проверка, примеры
+    Rules {
+        #[command(subcommand)]
+        action: RulesCmd,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum RulesCmd {
+    /// Список правил с тегами и файлом, откуда они взяты
+    List,
+    /// Замечания загрузки: облегчённые regex, пропущенные правила и исключения
+    Lint,
+    /// Прогнать truePositives / falsePositives каждого правила
+    Test,
 }
 
 #[derive(Parser, Debug)]
@@ -61,6 +121,452 @@ struct Cli {
     /// Порт сервера (для основного режима)
     #[arg(long, value_name = "PORT", default_value_t = 8000)]
     port: u16,
+
+    /// Глубина обхода: 0 — только URL из Wayback, N — ещё N переходов по ссылкам
+    #[arg(long, value_name = "N", default_value_t = 1)]
+    depth: usize,
+
+    /// Правило in-scope (повторяемый): `*.example.com`, `re:REGEX`, `cidr:NET`, `/path`.
+    /// Без правил по хосту (glob, re:, cidr:) в скоуп добавляются DOMAIN и его поддомены
+    #[arg(long = "scope", value_name = "RULE")]
+    scope: Vec<String>,
+
+    /// Правило out-of-scope (повторяемый), синтаксис как у --scope
+    #[arg(long = "out-of-scope", value_name = "RULE")]
+    out_of_scope: Vec<String>,
+
+    /// Файл скоупа: по правилу на строку, `!` — исключение, `#` — комментарий
+    #[arg(long, value_name = "FILE")]
+    scope_file: Option<PathBuf>,
+
+    /// Запросов в секунду на один хост (0 — без ограничения)
+    #[arg(long, value_name = "RPS", default_value_t = 5.0)]
+    rate: f64,
+
+    /// Одновременных запросов на весь скан
+    #[arg(long, value_name = "N", default_value_t = 16)]
+    max_in_flight: usize,
+
+    /// Сколько URL обрабатывать одновременно
+    #[arg(long, value_name = "N", default_value_t = 4)]
+    concurrency: usize,
+
+    /// Таймаут соединения, секунд
+    #[arg(long, value_name = "SECS", default_value_t = 10)]
+    connect_timeout: u64,
+
+    /// Таймаут ответа при загрузке страницы, секунд
+    #[arg(long, value_name = "SECS", default_value_t = 15)]
+    timeout: u64,
+
+    /// Повторы при таймауте или ошибке соединения (пауза растёт вдвое)
+    #[arg(long, value_name = "N", default_value_t = 1)]
+    retries: usize,
+
+    /// User-Agent для всех запросов
+    #[arg(long, value_name = "UA", default_value = "curl/8.4.0")]
+    user_agent: String,
+
+    /// Максимум редиректов (0 — не следовать)
+    #[arg(long, value_name = "N", default_value_t = 10)]
+    max_redirects: usize,
+
+    /// Вышестоящий прокси для всех запросов и Chrome: http://127.0.0.1:8080, socks5://…
+    #[arg(long, value_name = "URL")]
+    proxy: Option<String>,
+
+    /// CA-сертификат прокси (PEM/DER) для HTTP-клиента; Chrome его не получает
+    #[arg(long, value_name = "FILE")]
+    ca_cert: Option<PathBuf>,
+
+    /// Chrome не проверяет TLS-сертификаты никаких хостов (скриншоты через
+    /// перехватывающий прокси)
+    #[arg(long)]
+    browser_insecure: bool,
+
+    /// Заголовок для запросов к хостам из скоупа (повторяемый): "Name: value"
+    #[arg(long = "header", value_name = "HEADER")]
+    headers: Vec<String>,
+
+    /// Файл cookies в формате Netscape (cookies.txt)
+    #[arg(long, value_name = "FILE")]
+    cookies: Option<PathBuf>,
+
+    /// Bearer-токен для заголовка Authorization
+    #[arg(long, value_name = "TOKEN")]
+    bearer: Option<String>,
+
+    /// Источник URL (повторяемый): wayback, commoncrawl, otx, urlscan.
+    /// Без --source и --urls-file — только wayback
+    #[arg(long = "source", value_name = "NAME")]
+    sources: Vec<String>,
+
+    /// Базовый URL источника (повторяемый), например для своего зеркала: NAME=URL
+    #[arg(long = "source-base", value_name = "NAME=URL")]
+    source_bases: Vec<String>,
+
+    /// Файл со списком URL, по одному на строку (повторяемый)
+    #[arg(long = "urls-file", value_name = "FILE")]
+    urls_files: Vec<PathBuf>,
+
+    /// Ключ API urlscan.io
+    #[arg(long, value_name = "KEY")]
+    urlscan_key: Option<String>,
+
+    /// Сколько страниц выдачи читать у commoncrawl, otx и urlscan
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_MAX_PAGES)]
+    source_pages: usize,
+
+    /// Wayback: снимки не раньше даты (yyyy[MM[dd[hhmmss]]])
+    #[arg(long, value_name = "DATE")]
+    cdx_from: Option<String>,
+
+    /// Wayback: снимки не позже даты
+    #[arg(long, value_name = "DATE")]
+    cdx_to: Option<String>,
+
+    /// Wayback: статус ответа, регулярка CDX (повторяемый; `!404` — исключить)
+    #[arg(long, value_name = "CODE")]
+    cdx_status: Vec<String>,
+
+    /// Wayback: MIME-тип, регулярка CDX (повторяемый; `!image/.*` — исключить)
+    #[arg(long, value_name = "MIME")]
+    cdx_mime: Vec<String>,
+
+    /// Анализировать исторические снимки каждого URL: N — до N снимков равномерно
+    /// по времени, `digests` или `digests:N` — каждый снимок с новым содержимым
+    #[arg(long, value_name = "MODE")]
+    history: Option<String>,
+
+    /// Дополнительный файл правил в формате gitleaks (повторяемый); поддерживает
+    /// `[extend]` с `disabledRules` и `useDefault`
+    #[arg(long = "rules", value_name = "FILE", global = true)]
+    rules: Vec<PathBuf>,
+
+    /// Проверить найденные ключи у провайдеров (GitHub, Slack, Stripe, SendGrid).
+    /// Ключи уходят на их API — включать только осознанно
+    #[arg(long, action = ArgAction::SetTrue, global = true)]
+    verify: bool,
+
+    /// Базовый URL API проверки (повторяемый), например локальная заглушка: NAME=URL
+    #[arg(long = "verify-base", value_name = "NAME=URL", global = true)]
+    verify_bases: Vec<String>,
+
+    /// Подбирать HMAC-секрет найденных JWT по встроенному словарю слабых секретов
+    #[arg(long, action = ArgAction::SetTrue, global = true)]
+    jwt_crack: bool,
+
+    /// Свой словарь для подбора секрета JWT, по секрету на строку (включает --jwt-crack)
+    #[arg(long, value_name = "FILE", global = true)]
+    jwt_wordlist: Option<PathBuf>,
+}
+
+/// Учётные данные из флагов; отдаются только хостам из скоупа.
+fn credentials(args: &Cli) -> Result<Credentials> {
+    let mut creds = Credentials {
+        bearer: args.bearer.clone(),
+        ..Credentials::default()
+    };
+    for h in &args.headers {
+        creds.add_header(h)?;
+    }
+    if let Some(file) = &args.cookies {
+        creds.load_cookie_file(file)?;
+    }
+    Ok(creds)
+}
+
+/// Параметры скана из флагов CLI.
+fn scan_config(args: &Cli, domain: &str, scope: Scope) -> Result<ScanConfig> {
+    let redirects = match args.max_redirects {
+        0 => Redirects::None,
+        n => Redirects::Limit(n),
+    };
+
+    let mut config = ScanConfig::new(domain)
+        .depth(args.depth)
+        .concurrency(args.concurrency)
+        .connect_timeout(Duration::from_secs(args.connect_timeout))
+        .read_timeout(Duration::from_secs(args.timeout))
+        .retries(args.retries)
+        .user_agent(&args.user_agent)
+        .redirects(redirects)
+        .scope(scope)
+        .rate_limit(rate_limit(args))
+        .credentials(credentials(args)?)
+        .sources(url_sources(args)?)
+        .snapshots(snapshots(args)?);
+
+    if let Some(v) = verifiers(args)? {
+        config = config.verify(v);
+    }
+
+    if let Some(proxy) = &args.proxy {
+        config = config.proxy(proxy);
+    }
+    if let Some(ca) = &args.ca_cert {
+        config = config.ca_cert(ca.clone());
+    }
+    if args.browser_insecure {
+        eprintln!("[!] --browser-insecure: Chrome не проверяет TLS-сертификаты при скриншотах");
+        config = config.browser_insecure(true);
+    }
+    Ok(config)
+}
+
+/// Проверяльщики ключей из флагов; без `--verify` — `None`.
+fn verifiers(args: &Cli) -> Result<Option<Verifiers>> {
+    if !args.verify {
+        return Ok(None);
+    }
+    let mut out = Verifiers::builtin();
+    for raw in &args.verify_bases {
+        let (name, url) = raw
+            .split_once('=')
+            .ok_or_else(|| anyhow!("--verify-base ожидает NAME=URL: {raw}"))?;
+        let base_url = url.trim().to_string();
+        out = match name.trim().to_ascii_lowercase().as_str() {
+            "github" => out.with(GitHubVerifier { base_url }),
+            "slack" => out.with(SlackVerifier { base_url }),
+            "stripe" => out.with(StripeVerifier { base_url }),
+            "sendgrid" => out.with(SendGridVerifier { base_url }),
+            other => {
+                return Err(anyhow!(
+                    "неизвестный проверяльщик '{other}' (есть: {})",
+                    out.names().join(", ")
+                ))
+            }
+        };
+    }
+    Ok(Some(out))
+}
+
+/// Клиент для проверки ключей вне скана: прокси, CA и лимиты из флагов.
+/// Скоупа здесь нет (`Scope::new()` пускает любой хост), поэтому `--header`,
+/// `--cookies` и `--bearer` к API провайдеров не уходят.
+fn verify_fetcher(args: &Cli) -> Result<Fetcher> {
+    let config = scan_config(args, "", Scope::new())?.credentials(Credentials::default());
+    Fetcher::from_config(&config)
+}
+
+/// Источники URL из флагов.
+fn url_sources(args: &Cli) -> Result<Vec<Arc<dyn UrlSource>>> {
+    let mut bases = HashMap::new();
+    for raw in &args.source_bases {
+        let (name, url) = raw
+            .split_once('=')
+            .ok_or_else(|| anyhow!("--source-base ожидает NAME=URL: {raw}"))?;
+        bases.insert(name.trim().to_ascii_lowercase(), url.trim().to_string());
+    }
+    let base = |name: &str, default: String| bases.get(name).cloned().unwrap_or(default);
+
+    let mut names = args.sources.clone();
+    if names.is_empty() && args.urls_files.is_empty() {
+        names.push("wayback".to_string());
+    }
+
+    let mut out: Vec<Arc<dyn UrlSource>> = Vec::new();
+    for name in names {
+        let name = name.to_ascii_lowercase();
+        let src: Arc<dyn UrlSource> = match name.as_str() {
+            "wayback" => Arc::new(WaybackCdx {
+                base_url: base("wayback", WaybackCdx::default().base_url),
+                filter: cdx_filter(args),
+                ..WaybackCdx::default()
+            }),
+            "commoncrawl" => Arc::new(CommonCrawl {
+                base_url: base("commoncrawl", CommonCrawl::default().base_url),
+                max_pages: args.source_pages,
+                ..CommonCrawl::default()
+            }),
+            "otx" => Arc::new(AlienVaultOtx {
+                base_url: base("otx", AlienVaultOtx::default().base_url),
+                max_pages: args.source_pages,
+            }),
+            "urlscan" => Arc::new(UrlScan {
+                base_url: base("urlscan", UrlScan::default().base_url),
+                api_key: args.urlscan_key.clone(),
+                max_pages: args.source_pages,
+            }),
+            other => return Err(anyhow!("неизвестный источник URL: {other}")),
+        };
+        out.push(src);
+    }
+    for path in &args.urls_files {
+        out.push(Arc::new(LocalFile { path: path.clone() }));
+    }
+    Ok(out)
+}
+
+fn snapshots(args: &Cli) -> Result<Snapshots> {
+    let spec = match &args.history {
+        Some(s) => s.trim(),
+        None => return Ok(Snapshots::Latest),
+    };
+    let parse = |n: &str| {
+        n.parse::<usize>()
+            .map_err(|_| anyhow!("--history ожидает N, digests или digests:N: {spec}"))
+    };
+    match spec.split_once(':') {
+        _ if spec == "digests" => Ok(Snapshots::Digests(0)),
+        Some(("digests", n)) => Ok(Snapshots::Digests(parse(n)?)),
+        _ => Ok(Snapshots::Spread(parse(spec)?)),
+    }
+}
+
+fn cdx_filter(args: &Cli) -> CdxFilter {
+    CdxFilter {
+        from: args.cdx_from.clone(),
+        to: args.cdx_to.clone(),
+        statuses: args.cdx_status.clone(),
+        mimetypes: args.cdx_mime.clone(),
+    }
+}
+
+fn rate_limit(args: &Cli) -> RateLimit {
+    RateLimit {
+        per_host_rps: args.rate,
+        burst: args.rate.ceil().max(1.0) as u32,
+        max_in_flight: args.max_in_flight,
+        ..RateLimit::default()
+    }
+}
+
+/// Положить статусы URL из папки скана рядом с отчётом — для раздела
+/// «Только в архиве».
+fn attach_url_status(scan_dir: &Path, out_dir: &Path) {
+    let src = scan_dir.join(URL_STATUS_FILE);
+    if src.is_file() {
+        if let Err(e) = fs::copy(&src, out_dir.join(URL_STATUS_FILE)) {
+            eprintln!("Не скопировать {}: {e}", src.display());
+        }
+    }
+}
+
+async fn secrets_cmd(
+    args: &Cli,
+    paths: &[PathBuf],
+    jsonl: Option<&Path>,
+    sarif: Option<&Path>,
+) -> Result<()> {
+    let mut findings: Vec<Finding> = Vec::new();
+    let mut total = LocalScanStats::default();
+    for path in paths {
+        let stats = scan_path(path, &mut |f| {
+            findings.push(f);
+            Ok(())
+        })?;
+        total.files += stats.files;
+        total.skipped += stats.skipped;
+        total.findings += stats.findings;
+    }
+
+    if let Some(v) = verifiers(args)? {
+        let stats = v.verify_all(&verify_fetcher(args)?, &mut findings).await;
+        eprintln!(
+            "[+] Проверка ключей: рабочих {}, отклонено {}, ошибок {}",
+            stats.verified, stats.rejected, stats.errors
+        );
+    }
+
+    let mut w = match jsonl {
+        Some(file) => JsonlWriter::create(file)?,
+        None => JsonlWriter::stdout(),
+    };
+    for f in &findings {
+        w.write(f)?;
+    }
+    w.flush()?;
+    if let Some(file) = sarif {
+        write_sarif(&findings, file)?;
+    }
+
+    eprintln!(
+        "[+] Файлов: {}, пропущено: {}, находок: {}",
+        total.files, total.skipped, total.findings
+    );
+    Ok(())
+}
+
+fn rules_cmd(action: &RulesCmd, set: &RuleSet) -> Result<()> {
+    match action {
+        RulesCmd::List => {
+            for r in &set.rules {
+                let mut mark = String::new();
+                if r.fallback.is_some() {
+                    mark.push_str(" (lightweight)");
+                }
+                if !r.required.is_empty() {
+                    let ids: Vec<&str> = r.required.iter().map(|q| q.id.as_str()).collect();
+                    mark.push_str(&format!(" (+ {})", ids.join(", ")));
+                }
+                if r.skip_report {
+                    mark.push_str(" (skipReport)");
+                }
+                println!("{}{mark}\t{}\t{}", r.id, r.tags.join(","), r.origin);
+            }
+            println!("Правил: {}", set.len());
+        }
+        RulesCmd::Lint => {
+            for n in &set.notes {
+                // ошибки regex многострочные, в выводе lint — одной строкой
+                let message: Vec<&str> = n.message.lines().map(str::trim).collect();
+                println!("[{}] {} ({}): {}", n.kind.as_str(), n.rule_id, n.origin, message.join(" "));
+            }
+            // такие правила не отсекаются предфильтром и гоняются на каждом документе
+            for r in set.rules.iter().filter(|r| r.keywords.is_empty()) {
+                println!("[no-keywords] {} ({}): запускается на каждом документе", r.id, r.origin);
+            }
+            let untested = set
+                .rules
+                .iter()
+                .filter(|r| r.true_positives.is_empty() && r.false_positives.is_empty())
+                .count();
+            println!(
+                "Правил: {}, замечаний: {}, без примеров: {untested}",
+                set.len(),
+                set.notes.len()
+            );
+        }
+        RulesCmd::Test => {
+            let (total, failures) = set.test_examples();
+            for f in &failures {
+                let kind = if f.expected_match { "truePositives" } else { "falsePositives" };
+                println!("FAIL {} [{kind}] {:?}: {}", f.rule_id, f.example, f.reason);
+            }
+            println!("Примеров: {total}, провалено: {}", failures.len());
+            if !failures.is_empty() {
+                return Err(anyhow!("провалено примеров: {}", failures.len()));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Собрать скоуп из флагов; без include-правил по хосту (например, только
+/// `--scope /api/`) — DOMAIN и его поддомены.
+fn build_scope(args: &Cli, domain: &str) -> Result<Scope> {
+    let mut scope = Scope::new();
+    add_scope_flags(args, &mut scope)?;
+
+    if !scope.has_host_includes() {
+        scope.include_domain(domain);
+    }
+    Ok(scope)
+}
+
+/// Добавить в скоуп правила из --scope-file, --scope и --out-of-scope.
+fn add_scope_flags(args: &Cli, scope: &mut Scope) -> Result<()> {
+    if let Some(file) = &args.scope_file {
+        scope.load_file(file)?;
+    }
+    for rule in &args.scope {
+        scope.include(rule)?;
+    }
+    for rule in &args.out_of_scope {
+        scope.exclude(rule)?;
+    }
+    Ok(())
 }
 
 #[tokio::main]
@@ -78,6 +584,84 @@ async fn main() -> Result<()> {
         return Ok(());
     }
 
+    // --- Подкоманда: правила ---
+    if let Some(Cmd::Rules { action }) = &args.cmd {
+        let set = RuleSet::load(&args.rules)?;
+        return rules_cmd(action, &set);
+    }
+
+    // встроенные правила грузятся и без --rules: ошибка в них останавливает запуск
+    let rules = core::rules::init_rules(&args.rules)?;
+    if !args.rules.is_empty() {
+        eprintln!("[+] Правил загружено: {}", rules.len());
+    }
+
+    if args.jwt_crack || args.jwt_wordlist.is_some() {
+        let n = core::jwt::enable_cracking(args.jwt_wordlist.as_deref())?;
+        eprintln!("[+] Подбор секретов JWT: {n} слов");
+    }
+
+    // --- Подкоманда: секреты в локальных файлах ---
+    if let Some(Cmd::Secrets { paths, jsonl, sarif }) = &args.cmd {
+        return secrets_cmd(&args, paths, jsonl.as_deref(), sarif.as_deref()).await;
+    }
+
+    // --- Подкоманда: повторный анализ сохранённого скана ---
+    if let Some(Cmd::Reanalyze { dir }) = &args.cmd {
+        let stats = reanalyze(dir).await?;
+        println!(
+            "[+] Файлов: {} (без исходного URL: {}), находок: {} ({})",
+            stats.files,
+            stats.unmapped,
+            stats.findings,
+            dir.join("findings.sarif").display()
+        );
+        if let Some(v) = verifiers(&args)? {
+            let paths = scanner::Paths::new(&dir.to_string_lossy()).map_err(|e| anyhow!("{e}"))?;
+            let stats = verify_scan(&paths, &verify_fetcher(&args)?, &v).await?;
+            println!(
+                "[+] Проверка ключей: рабочих {}, отклонено {}, ошибок {}",
+                stats.verified, stats.rejected, stats.errors
+            );
+        }
+        return Ok(());
+    }
+
+    // --- Подкоманда: импорт скоупа программы ---
+    if let Some(Cmd::Import { file, write_scope, scan }) = &args.cmd {
+        let imported = ImportedScope::load(file)?;
+
+        println!("Цели:");
+        for t in imported.targets() {
+            println!("  {t}");
+        }
+        println!("Исключения:");
+        for a in imported.assets.iter().filter(|a| !a.in_scope) {
+            println!("  {}", a.identifier);
+        }
+
+        if let Some(out) = write_scope {
+            fs::write(out, imported.to_scope_file())
+                .map_err(|e| anyhow!("Запись {}: {e}", out.display()))?;
+            println!("Файл скоупа: {}", out.display());
+        }
+
+        if *scan {
+            // флаги скоупа дополняют импортированный: например, --out-of-scope
+            let mut scope = imported.to_scope()?;
+            add_scope_flags(&args, &mut scope)?;
+            for domain in imported.wildcard_domains() {
+                println!("Скан {domain}...");
+                let config = scan_config(&args, &domain, scope.clone())?;
+                match run_scan_with(config).await {
+                    Ok(paths) => println!("Скан завершён. Результаты: {}", paths.base.display()),
+                    Err(e) => eprintln!("Ошибка скана {domain}: {e}"),
+                }
+            }
+        }
+        return Ok(());
+    }
+
     // --- Валидация для основного режима ---
     // допускаются два пути:
     //   1) --images DIR (тогда domain не обязателен)
@@ -105,6 +689,10 @@ async fn main() -> Result<()> {
         let runner = EyeballerRunner::new(&args.model, Labels::eyeballer_default())?;
         let (_csv, html) =
             runner.infer_to_csv_html(&images_dir, &out_dir, "predictions.csv", None)?;
+        // DOMAIN/screenshots → статусы лежат в DOMAIN
+        if let Some(scan_dir) = images_dir.parent() {
+            attach_url_status(scan_dir, &out_dir);
+        }
         println!("Отчёт: {}", html.display());
 
         if args.serve {
@@ -117,7 +705,11 @@ async fn main() -> Result<()> {
     // --- Режим 2: полный цикл — скан → (опц.) анализ ---
     let domain = args.domain.as_deref().unwrap(); // к этому месту гарантированно Some
 
-    let paths = run_scan(domain).await.map_err(|e| anyhow!(e.to_string()))?;
+    let scope = build_scope(&args, domain)?;
+
+    let paths = run_scan_with(scan_config(&args, domain, scope)?)
+        .await
+        .map_err(|e| anyhow!(e.to_string()))?;
     println!("Скан завершён. Результаты: {}", paths.base.display());
 
     if args.analyze {
@@ -133,6 +725,7 @@ async fn main() -> Result<()> {
         let runner = EyeballerRunner::new(&args.model, Labels::eyeballer_default())?;
         let (_csv, html) =
             runner.infer_to_csv_html(&images_dir, &out_dir, "predictions.csv", None)?;
+        attach_url_status(&paths.base, &out_dir);
         println!("Отчёт: {}", html.display());
 
         if args.serve {
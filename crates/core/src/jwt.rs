@@ -0,0 +1,412 @@
+use crate::findings::Finding;
+use crate::patterns::{PatternSpec, line_around, shannon_entropy, should_ignore_value};
+use crate::rules::RuleSet;
+
+use anyhow::{Context, Result as AnyResult};
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use hmac::{Hmac, Mac};
+use once_cell::sync::{Lazy, OnceCell};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sha2::{Sha256, Sha384, Sha512};
+use std::{
+    fs,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Срок жизни, после которого токен считается долгоживущим.
+const LONG_LIVED_SECS: i64 = 30 * 24 * 3600;
+
+/// Встроенный словарь для `--jwt-crack`: секреты из примеров и туториалов.
+const WEAK_SECRETS: &[&str] = &[
+    "secret",
+    "Secret",
+    "SECRET",
+    "secretkey",
+    "secret_key",
+    "secret-key",
+    "jwt",
+    "jwtsecret",
+    "jwt_secret",
+    "jwt-secret",
+    "your-256-bit-secret",
+    "your-384-bit-secret",
+    "your-512-bit-secret",
+    "your_jwt_secret",
+    "mysecret",
+    "my_secret",
+    "supersecret",
+    "changeme",
+    "changeit",
+    "password",
+    "123456",
+    "12345678",
+    "qwerty",
+    "admin",
+    "test",
+    "key",
+    "private",
+    "default",
+    "development",
+    "shhhhh",
+    "keyboard cat",
+];
+
+/// Ключи claims, в которых обычно лежат права.
+const ROLE_CLAIMS: &[&str] = &[
+    "role",
+    "roles",
+    "groups",
+    "scope",
+    "scopes",
+    "scp",
+    "permissions",
+    "authorities",
+    "admin",
+    "is_admin",
+    "isAdmin",
+];
+
+/// Header и payload — base64url JSON, подпись может быть пустой (`alg: none`).
+static JWT_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"\bey[A-Za-z0-9_-]{10,}\.ey[A-Za-z0-9_-]{2,}\.[A-Za-z0-9_-]*")
+        .expect("BUG: неверный regex JWT")
+});
+
+static EMAIL_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}$").expect("BUG: неверный regex email")
+});
+
+static WORDLIST: OnceCell<Vec<String>> = OnceCell::new();
+
+/// Включить подбор HMAC-секрета: `None` — встроенный словарь, иначе файл
+/// (по секрету на строку). Без вызова подбор не выполняется.
+pub fn enable_cracking(wordlist: Option<&Path>) -> AnyResult<usize> {
+    let words: Vec<String> = match wordlist {
+        Some(path) => fs::read_to_string(path)
+            .with_context(|| format!("чтение словаря {}", path.display()))?
+            .lines()
+            .map(|l| l.trim_end_matches('\r').to_string())
+            .filter(|l| !l.is_empty())
+            .collect(),
+        None => WEAK_SECRETS.iter().map(|s| s.to_string()).collect(),
+    };
+    let n = words.len();
+    let _ = WORDLIST.set(words);
+    Ok(n)
+}
+
+/// Разобранный JWT и что в нём опасного.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JwtInfo {
+    pub header: Map<String, Value>,
+    pub claims: Map<String, Value>,
+    /// Коды проблем: `alg-none`, `symmetric-alg`, `no-exp`, `long-lived`,
+    /// `email`, `roles`, `weak-secret`.
+    #[serde(default)]
+    pub issues: Vec<String>,
+    /// HMAC-секрет из словаря, которым подписан токен.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weak_secret: Option<String>,
+}
+
+impl JwtInfo {
+    pub fn alg(&self) -> &str {
+        self.header.get("alg").and_then(Value::as_str).unwrap_or("")
+    }
+}
+
+fn decode_object(part: &str) -> Option<Map<String, Value>> {
+    let raw = URL_SAFE_NO_PAD.decode(part.trim_end_matches('=')).ok()?;
+    match serde_json::from_slice(&raw).ok()? {
+        Value::Object(map) => Some(map),
+        _ => None,
+    }
+}
+
+/// Разобрать JWS `header.payload.signature`; `None` — это не JWT.
+pub fn analyze(token: &str) -> Option<JwtInfo> {
+    let mut parts = token.trim_end_matches('=').split('.');
+    let (h, p, sig) = (parts.next()?, parts.next()?, parts.next()?);
+    if parts.next().is_some() {
+        return None;
+    }
+    let header = decode_object(h)?;
+    let claims = decode_object(p)?;
+    let alg = header.get("alg").and_then(Value::as_str)?.to_string();
+
+    let mut issues = Vec::new();
+    if alg.eq_ignore_ascii_case("none") {
+        issues.push("alg-none".to_string());
+    } else if alg.to_ascii_uppercase().starts_with("HS") {
+        issues.push("symmetric-alg".to_string());
+    }
+
+    let num = |k: &str| claims.get(k).and_then(Value::as_f64).map(|v| v as i64);
+    match (num("exp"), num("iat")) {
+        (None, _) => issues.push("no-exp".to_string()),
+        (Some(exp), Some(iat)) if exp - iat > LONG_LIVED_SECS => issues.push("long-lived".to_string()),
+        (Some(exp), None) if exp - now() > LONG_LIVED_SECS => issues.push("long-lived".to_string()),
+        _ => {}
+    }
+
+    if claims.values().any(has_email) {
+        issues.push("email".to_string());
+    }
+    if ROLE_CLAIMS.iter().any(|k| claims.contains_key(*k)) {
+        issues.push("roles".to_string());
+    }
+
+    let weak_secret = WORDLIST
+        .get()
+        .and_then(|words| crack(&alg, &format!("{h}.{p}"), sig, words));
+    if weak_secret.is_some() {
+        issues.push("weak-secret".to_string());
+    }
+
+    Some(JwtInfo {
+        header,
+        claims,
+        issues,
+        weak_secret,
+    })
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+fn has_email(v: &Value) -> bool {
+    match v {
+        Value::String(s) => EMAIL_RE.is_match(s),
+        Value::Array(a) => a.iter().any(has_email),
+        Value::Object(o) => o.values().any(has_email),
+        _ => false,
+    }
+}
+
+/// Подобрать HMAC-секрет из словаря под подпись `sig` над `signed`.
+fn crack(alg: &str, signed: &str, sig: &str, words: &[String]) -> Option<String> {
+    let sig = URL_SAFE_NO_PAD.decode(sig).ok()?;
+    let hmac_of = |key: &[u8]| -> Option<Vec<u8>> {
+        macro_rules! sign {
+            ($h:ty) => {{
+                let mut mac = Hmac::<$h>::new_from_slice(key).ok()?;
+                mac.update(signed.as_bytes());
+                Some(mac.finalize().into_bytes().to_vec())
+            }};
+        }
+        match alg {
+            "HS256" => sign!(Sha256),
+            "HS384" => sign!(Sha384),
+            "HS512" => sign!(Sha512),
+            _ => None,
+        }
+    };
+    words
+        .iter()
+        .find(|w| hmac_of(w.as_bytes()).is_some_and(|s| s == sig))
+        .cloned()
+}
+
+/// Отдельный проход за JWT: найденные правилами токены получают разбор,
+/// а пропущенные (например, неподписанные `alg: none`) добавляются как
+/// находки правила `jwt`, если проходят его энтропию и исключения.
+pub fn annotate(findings: &mut Vec<Finding>, text: &str, location: &str) -> AnyResult<()> {
+    annotate_with(crate::rules::rules()?, findings, text, location);
+    Ok(())
+}
+
+fn annotate_with(set: &RuleSet, findings: &mut Vec<Finding>, text: &str, location: &str) {
+    let jwt_rule = set.rules.iter().find(|r| r.id == "jwt");
+    for m in JWT_RE.find_iter(text) {
+        let token = m.as_str();
+        let Some(info) = analyze(token) else { continue };
+
+        let same = |f: &Finding| f.secret.trim_end_matches('=') == token.trim_end_matches('=');
+        let hits: Vec<usize> = findings
+            .iter()
+            .enumerate()
+            .filter(|(_, f)| same(f))
+            .map(|(i, _)| i)
+            .collect();
+        if !hits.is_empty() {
+            for i in hits {
+                findings[i].jwt = Some(info.clone());
+            }
+            continue;
+        }
+        // правило `jwt` выключено (`disabledRules`, `useDefault = false`) — не добавляем
+        let Some(spec) = jwt_rule else { continue };
+        if !rule_accepts(spec, text, m.start(), m.end(), location) {
+            continue;
+        }
+
+        let mut f = Finding::new("jwt", "JSON Web Token", location, text, m.start(), token, token);
+        f.tags = vec!["jwt".to_string()];
+        f.jwt = Some(info);
+        findings.push(f);
+    }
+}
+
+/// Те же фильтры, что проходит совпадение правила в `scan_rule`: значения,
+/// энтропия и исключения. Regex правила может не взять токен целиком
+/// (пустая подпись `alg: none`) — тогда энтропия считается по всему токену.
+fn rule_accepts(spec: &PatternSpec, text: &str, start: usize, end: usize, location: &str) -> bool {
+    let token = &text[start..end];
+    let entropy_ok = match spec.re.captures(token) {
+        Some(cap) => spec.entropy_ok(&cap),
+        None => spec.entropy.is_none_or(|min| shannon_entropy(token.as_bytes()) >= min),
+    };
+    entropy_ok
+        && !should_ignore_value(token)
+        && !spec.allowed(token, token, line_around(text, start, end), location)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn part(json: &str) -> String {
+        URL_SAFE_NO_PAD.encode(json)
+    }
+
+    /// Токен с подписью HS256 ключом `key`; без ключа — пустая подпись.
+    fn token(header: &str, claims: &str, key: Option<&str>) -> String {
+        let signed = format!("{}.{}", part(header), part(claims));
+        let sig = key
+            .map(|k| {
+                let mut mac = Hmac::<Sha256>::new_from_slice(k.as_bytes()).unwrap();
+                mac.update(signed.as_bytes());
+                URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes())
+            })
+            .unwrap_or_default();
+        format!("{signed}.{sig}")
+    }
+
+    fn rule_set(name: &str, toml: &str) -> RuleSet {
+        let path = std::env::temp_dir().join(format!("webhound-{name}-{}.toml", std::process::id()));
+        fs::write(&path, toml).unwrap();
+        let set = RuleSet::load(&[PathBuf::from(&path)]);
+        let _ = fs::remove_file(&path);
+        set.unwrap()
+    }
+
+    #[test]
+    fn analyze_flags_alg_none_and_missing_exp() {
+        let info = analyze(&token(r#"{"alg":"none"}"#, r#"{"sub":"42"}"#, None)).unwrap();
+        assert_eq!(info.alg(), "none");
+        assert_eq!(info.issues, ["alg-none", "no-exp"]);
+
+        let info = analyze(&token(
+            r#"{"alg":"HS256"}"#,
+            r#"{"sub":"42","email":"a@ex.com","role":"admin","iat":1700000000,"exp":1700003600}"#,
+            Some("k"),
+        ))
+        .unwrap();
+        assert_eq!(info.issues, ["symmetric-alg", "email", "roles"]);
+
+        assert!(analyze("eyJub3QiOiJqd3QifQ.notjson.sig").is_none());
+        assert!(analyze(&token(r#"{"typ":"JWT"}"#, r#"{"sub":"42"}"#, None)).is_none());
+    }
+
+    #[test]
+    fn analyze_flags_long_lived_tokens() {
+        let iat = 1_700_000_000;
+        let long = format!(r#"{{"iat":{iat},"exp":{}}}"#, iat + LONG_LIVED_SECS + 1);
+        let info = analyze(&token(r#"{"alg":"RS256"}"#, &long, None)).unwrap();
+        assert_eq!(info.issues, ["long-lived"]);
+
+        let exact = format!(r#"{{"iat":{iat},"exp":{}}}"#, iat + LONG_LIVED_SECS);
+        let info = analyze(&token(r#"{"alg":"RS256"}"#, &exact, None)).unwrap();
+        assert!(info.issues.is_empty(), "{:?}", info.issues);
+
+        // без iat срок считается от текущего момента
+        let far = format!(r#"{{"exp":{}}}"#, now() + 2 * LONG_LIVED_SECS);
+        let info = analyze(&token(r#"{"alg":"RS256"}"#, &far, None)).unwrap();
+        assert_eq!(info.issues, ["long-lived"]);
+    }
+
+    #[test]
+    fn crack_finds_hmac_secret_from_wordlist() {
+        let t = token(r#"{"alg":"HS256"}"#, r#"{"sub":"42"}"#, Some("changeme"));
+        let (signed, sig) = t.rsplit_once('.').unwrap();
+        let words: Vec<String> = ["secret", "changeme"].iter().map(|s| s.to_string()).collect();
+
+        assert_eq!(crack("HS256", signed, sig, &words).as_deref(), Some("changeme"));
+        assert_eq!(crack("HS256", signed, sig, &words[..1]), None);
+        // подпись не того алгоритма не подбирается
+        assert_eq!(crack("HS512", signed, sig, &words), None);
+        assert_eq!(crack("RS256", signed, sig, &words), None);
+    }
+
+    const JWT_RULE: &str = r#"
+[extend]
+useDefault = false
+
+[[rules]]
+id = "jwt"
+regex = '''\b(ey[a-zA-Z0-9]{17,}\.ey[a-zA-Z0-9\/\\_-]{17,}\.(?:[a-zA-Z0-9\/\\_-]{10,}={0,2})?)(?:[\x60'"\s;]|\\[nr]|$)'''
+entropy = 3
+keywords = ["ey"]
+
+[[rules.allowlists]]
+paths = ['''(^|/)fixtures/''']
+"#;
+
+    #[test]
+    fn unsigned_token_is_added_unless_allowlisted() {
+        let set = rule_set("jwt-allowlist", JWT_RULE);
+        let t = token(r#"{"alg":"none","typ":"JWT"}"#, r#"{"sub":"1234567890","name":"John"}"#, None);
+        let text = format!("location.href = \"/cb#id_token={t}&state=1\";\n");
+        // за токеном `&`, а не кавычка или пробел, — правило его не берёт,
+        // и находку добавляет только annotate
+        assert!(set.scan(&text, "https://ex.com/app.js").is_empty());
+
+        let mut found = Vec::new();
+        annotate_with(&set, &mut found, &text, "https://ex.com/app.js");
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].rule_id, "jwt");
+        assert_eq!(found[0].secret, t);
+        assert!(found[0].jwt.as_ref().unwrap().issues.contains(&"alg-none".to_string()));
+
+        let mut found = Vec::new();
+        annotate_with(&set, &mut found, &text, "https://ex.com/fixtures/app.js");
+        assert!(found.is_empty(), "{found:?}");
+    }
+
+    #[test]
+    fn low_entropy_or_disabled_rule_adds_nothing() {
+        let set = rule_set("jwt-entropy", &JWT_RULE.replace("entropy = 3", "entropy = 5.5"));
+        let t = token(r#"{"alg":"none"}"#, r#"{"sub":"1"}"#, None);
+        let mut found = Vec::new();
+        annotate_with(&set, &mut found, &t, "https://ex.com/app.js");
+        assert!(found.is_empty(), "{found:?}");
+
+        let set = rule_set("jwt-disabled", &JWT_RULE.replace("useDefault = false", "useDefault = false\ndisabledRules = [\"jwt\"]"));
+        annotate_with(&set, &mut found, &t, "https://ex.com/app.js");
+        assert!(found.is_empty(), "{found:?}");
+    }
+
+    #[test]
+    fn existing_findings_get_the_analysis() {
+        let set = rule_set("jwt-existing", JWT_RULE);
+        let t = token(r#"{"alg":"HS256"}"#, r#"{"sub":"1234567890"}"#, Some("key"));
+        let text = format!("token = \"{t}\"");
+        let mut found: Vec<Finding> = set
+            .scan(&text, "https://ex.com/a.js")
+            .iter()
+            .map(|m| Finding::from_match(m, &text, "https://ex.com/a.js"))
+            .collect();
+        assert_eq!(found.len(), 1);
+        annotate_with(&set, &mut found, &text, "https://ex.com/a.js");
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].jwt.as_ref().unwrap().alg(), "HS256");
+    }
+}
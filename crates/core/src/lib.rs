@@ -1,5 +1,9 @@
+pub mod findings;
+pub mod jwt;
 pub mod patterns;
+pub mod rules;
 pub mod utils;
+pub mod validate;
 
 pub mod analysis;
 pub use analysis::PathsLike;
@@ -1,142 +1,392 @@
+use crate::rules::{AllowList, compile_with_bigger_limits};
+
+use aho_corasick::{AhoCorasick, AhoCorasickBuilder};
+use anyhow::{Result as AnyResult, bail};
 use once_cell::sync::Lazy;
-use regex::{Regex, RegexBuilder, RegexSet};
-use serde::Deserialize;
+use regex::{Regex, RegexSet};
 use std::borrow::Cow;
 
 #[derive(Debug)]
 pub struct PatternSpec {
     pub re: Regex,
+    /// `id` правила из ruls.toml.
+    pub id: String,
     pub name: String,
+    pub tags: Vec<String>,
     pub secret_group: Option<usize>,
+    /// Минимальная энтропия (бит на символ), ниже — совпадение отбрасывается.
+    pub entropy: Option<f64>,
+    /// Группа, по которой считается энтропия.
+    pub entropy_group: Option<usize>,
+    /// Исключения правила; совпадение, подошедшее под любое, отбрасывается.
+    pub allowlists: Vec<Allowlist>,
+    /// Правило запускается, только если в тексте есть хотя бы одно из слов
+    /// (без учёта регистра); пустой список — запускать всегда.
+    pub keywords: Vec<String>,
+    /// Файл, из которого взято правило.
+    pub origin: String,
+    /// Почему вместо regex правила работает облегчённый regex по keywords.
+    pub fallback: Option<String>,
+    /// Строки, на которых правило обязано сработать, и на которых не должно.
+    pub true_positives: Vec<String>,
+    pub false_positives: Vec<String>,
+    /// Составное правило: рядом с совпадением должны найтись совпадения
+    /// этих правил, иначе оно не срабатывает.
+    pub required: Vec<Required>,
+    /// Совпадения правила идут только в составные правила, отдельно не сообщаются.
+    pub skip_report: bool,
 }
 
-#[derive(Debug, Deserialize)]
-struct GitleaksConfig {
-    #[serde(default)]
-    rules: Vec<GitleaksRule>,
+impl PatternSpec {
+    /// Секрет в духе gitleaks: `secret_group`, иначе первая непустая группа,
+    /// иначе всё совпадение.
+    pub fn secret_of<'t>(&self, cap: &regex::Captures<'t>) -> &'t str {
+        self.secret_group
+            .and_then(|i| cap.get(i))
+            .map(|m| m.as_str())
+            .or_else(|| {
+                cap.iter()
+                    .skip(1)
+                    .flatten()
+                    .map(|m| m.as_str())
+                    .find(|s| !s.is_empty())
+            })
+            .or_else(|| cap.get(0).map(|m| m.as_str()))
+            .unwrap_or("")
+    }
+
+    /// Строка для проверки энтропии: `entropy_group`, иначе секрет.
+    pub fn entropy_target<'t>(&self, cap: &regex::Captures<'t>) -> &'t str {
+        self.entropy_group
+            .and_then(|i| cap.get(i))
+            .map(|m| m.as_str())
+            .unwrap_or_else(|| self.secret_of(cap))
+    }
+
+    /// Проходит ли совпадение порог энтропии правила.
+    pub fn entropy_ok(&self, cap: &regex::Captures) -> bool {
+        match self.entropy {
+            Some(min) => shannon_entropy(self.entropy_target(cap).as_bytes()) >= min,
+            None => true,
+        }
+    }
+
+    /// Подходит ли совпадение под одно из исключений правила.
+    pub fn allowed(&self, secret: &str, matched: &str, line: &str, location: &str) -> bool {
+        if self.allowlists.is_empty() {
+            return false;
+        }
+        let paths = path_candidates(location);
+        self.allowlists
+            .iter()
+            .any(|a| a.allows(secret, matched, line, &paths))
+    }
 }
 
-#[derive(Debug, Deserialize)]
-pub struct GitleaksRule {
+/// Часть составного правила (`[[rules.required]]`).
+#[derive(Debug, Clone)]
+pub struct Required {
     pub id: String,
-    pub description: String,
-    pub regex: String,
-
-    #[serde(default)]
-    pub report: Option<String>, // иногда есть короткий отчёт
+    /// Номер правила в `RuleSet::rules`, проставляется при загрузке.
+    pub(crate) index: usize,
+    /// Насколько далеко от основного совпадения может быть часть: в строках,
+    /// в колонках начала и в байтах между совпадениями; `None` — без ограничения.
+    pub within_lines: Option<usize>,
+    pub within_columns: Option<usize>,
+    pub within_bytes: Option<usize>,
+}
 
-    #[serde(default)]
-    pub tags: Vec<String>, // например ["key", "AWS"]
+impl Required {
+    /// Подходит ли совпадение `part` к основному `main` по расстоянию.
+    pub(crate) fn near(&self, main: &RuleMatch, part: &RuleMatch, lines: &LineIndex) -> bool {
+        let (ml, mc) = lines.line_col(main.start);
+        let (pl, pc) = lines.line_col(part.start);
+        self.within_lines.is_none_or(|n| ml.abs_diff(pl) <= n)
+            && self.within_columns.is_none_or(|n| mc.abs_diff(pc) <= n)
+            && self.within_bytes.is_none_or(|n| gap(main, part) <= n)
+    }
+}
 
-    #[serde(default)]
-    pub entropy: Option<f64>, // минимальная энтропия
+/// Сколько байт между двумя совпадениями; 0 — если они пересекаются.
+pub(crate) fn gap(a: &RuleMatch, b: &RuleMatch) -> usize {
+    if b.start >= a.end {
+        b.start - a.end
+    } else {
+        a.start.saturating_sub(b.end)
+    }
+}
 
-    #[serde(rename = "entropyGroup")]
-    pub entropy_group: Option<usize>,
+/// Начала строк текста, чтобы быстро переводить смещение в строку и колонку.
+pub(crate) struct LineIndex {
+    starts: Vec<usize>,
+}
 
-    #[serde(rename = "secretGroup")]
-    pub secret_group: Option<usize>,
+impl LineIndex {
+    pub(crate) fn new(text: &str) -> Self {
+        let starts = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        Self { starts }
+    }
 
-    #[serde(default)]
-    pub keywords: Vec<String>,
+    /// Строка и колонка (в байтах) смещения, с нуля.
+    pub(crate) fn line_col(&self, offset: usize) -> (usize, usize) {
+        let line = self.starts.partition_point(|&s| s <= offset).saturating_sub(1);
+        (line, offset - self.starts[line])
+    }
+}
 
-    #[serde(default)]
-    pub stopwords: Vec<String>, // слова, при которых можно игнорить
+/// С чем сравниваются `regexes` исключения.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RegexTarget {
+    /// Извлечённый секрет (по умолчанию).
+    #[default]
+    Secret,
+    /// Всё совпадение правила.
+    Match,
+    /// Вся строка, в которой найдено совпадение.
+    Line,
+}
 
-    #[serde(default)]
-    pub allowlists: Vec<AllowList>,
+/// Скомпилированный `[[rules.allowlists]]`.
+#[derive(Debug, Clone, Default)]
+pub struct Allowlist {
+    /// `condition = "AND"`: должны сработать все заданные проверки, иначе любая.
+    pub require_all: bool,
+    pub regex_target: RegexTarget,
+    pub regexes: Vec<Regex>,
+    /// Сверяются с URL, его путём и именем файла внутри архива.
+    pub paths: Vec<Regex>,
+    /// В нижнем регистре; ищутся подстрокой в секрете.
+    pub stopwords: Vec<String>,
 }
 
-#[derive(Debug, Deserialize)]
-pub struct AllowList {
-    #[serde(default)]
-    pub regexes: Vec<String>,
+impl Allowlist {
+    /// Без `strict` (встроенные правила) неподдерживаемые регулярки
+    /// пропускаются с записью в `skipped`, иначе это ошибка загрузки.
+    pub(crate) fn compile(
+        a: AllowList,
+        rule_id: &str,
+        strict: bool,
+        skipped: &mut Vec<String>,
+    ) -> AnyResult<Self> {
+        let mut compile_all = |pats: Vec<String>| -> AnyResult<Vec<Regex>> {
+            let mut out = Vec::with_capacity(pats.len());
+            for p in pats {
+                match compile_with_bigger_limits(&p) {
+                    Ok(re) => out.push(re),
+                    Err(e) if strict => bail!("правило '{rule_id}': исключение {p:?}: {e}"),
+                    Err(e) => skipped.push(format!("исключение {p:?} не компилируется: {e}")),
+                }
+            }
+            Ok(out)
+        };
+        let regexes = compile_all(a.regexes)?;
+        let paths = compile_all(a.paths)?;
+        let regex_target = match a.regex_target.as_deref().map(str::to_ascii_lowercase).as_deref() {
+            None | Some("secret") => RegexTarget::Secret,
+            Some("match") => RegexTarget::Match,
+            Some("line") => RegexTarget::Line,
+            Some(other) if strict => bail!("правило '{rule_id}': неизвестный regexTarget '{other}'"),
+            Some(other) => {
+                skipped.push(format!("неизвестный regexTarget '{other}', беру secret"));
+                RegexTarget::Secret
+            }
+        };
+        Ok(Self {
+            require_all: a.condition.as_deref().is_some_and(|c| c.eq_ignore_ascii_case("and")),
+            regex_target,
+            regexes,
+            paths,
+            stopwords: lowercase_stopwords(a.stopwords),
+        })
+    }
 
-    #[serde(default)]
-    pub paths: Vec<String>,
+    pub(crate) fn stopwords_only(words: Vec<String>) -> Self {
+        Self {
+            stopwords: lowercase_stopwords(words),
+            ..Self::default()
+        }
+    }
 
-    #[serde(default)]
-    pub commits: Vec<String>,
+    /// Коммитов у нас нет, поэтому `commits` не проверяются.
+    pub fn allows(&self, secret: &str, matched: &str, line: &str, paths: &[&str]) -> bool {
+        let mut checks: Vec<bool> = Vec::with_capacity(3);
+        if !self.paths.is_empty() {
+            checks.push(paths.iter().any(|p| self.paths.iter().any(|re| re.is_match(p))));
+        }
+        if !self.regexes.is_empty() {
+            let target = match self.regex_target {
+                RegexTarget::Secret => secret,
+                RegexTarget::Match => matched,
+                RegexTarget::Line => line,
+            };
+            checks.push(self.regexes.iter().any(|re| re.is_match(target)));
+        }
+        if !self.stopwords.is_empty() {
+            let lower = secret.to_lowercase();
+            checks.push(self.stopwords.iter().any(|w| lower.contains(w.as_str())));
+        }
 
-    #[serde(default)]
-    pub files: Vec<String>,
+        if checks.is_empty() {
+            false
+        } else if self.require_all {
+            checks.iter().all(|&c| c)
+        } else {
+            checks.iter().any(|&c| c)
+        }
+    }
 }
-const RULS_TOML: &str = config::RULS_TOML;
 
-fn compile_with_bigger_limits(pat: &str) -> Result<Regex, regex::Error> {
-    RegexBuilder::new(pat)
-        .size_limit(64 * 1024 * 1024) // 64 MiB на таблицы
-        .dfa_size_limit(64 * 1024 * 1024) // 64 MiB на DFA
-        .build()
+fn lowercase_stopwords(words: Vec<String>) -> Vec<String> {
+    words
+        .into_iter()
+        .map(|w| w.trim().to_lowercase())
+        .filter(|w| !w.is_empty())
+        .collect()
 }
 
-fn build_lightweight_regex_from_keywords(keywords: &[String]) -> Option<(Regex, usize)> {
-    if keywords.is_empty() {
-        return None;
+/// Варианты «пути» для `paths`: весь адрес, путь URL без ведущего `/` и,
+/// для `URL!файл`, имя файла в архиве.
+fn path_candidates(location: &str) -> Vec<&str> {
+    let mut out = vec![location];
+    let (outer, entry) = match location.split_once('!') {
+        Some((u, e)) => (u, Some(e)),
+        None => (location, None),
+    };
+    if let Some(rest) = outer.split_once("://").map(|(_, r)| r) {
+        let path = rest.find('/').map(|i| &rest[i + 1..]).unwrap_or("");
+        let path = path.split(['?', '#']).next().unwrap_or("");
+        if !path.is_empty() {
+            out.push(path);
+        }
     }
-    let alts: String = keywords
-        .iter()
-        .filter(|s| !s.trim().is_empty())
-        .map(|s| regex::escape(s))
-        .collect::<Vec<_>>()
-        .join("|");
+    out.extend(entry.filter(|e| !e.is_empty()));
+    out
+}
 
-    if alts.is_empty() {
-        return None;
-    }
+/// Строка текста, в которой лежит диапазон `start..end`.
+pub(crate) fn line_around(text: &str, start: usize, end: usize) -> &str {
+    let from = text[..start].rfind('\n').map(|i| i + 1).unwrap_or(0);
+    let to = text[end..].find('\n').map(|i| end + i).unwrap_or(text.len());
+    &text[from..to]
+}
 
-    let pat = format!(
-        r#"(?i)\b(?:{})(?:\W{{0,20}}[:=]\W{{0,20}}|\W{{1,20}})?(['\"]?)([A-Za-z0-9_\-]{{20,}})(\1)?"#,
-        alts
-    );
-    match compile_with_bigger_limits(&pat) {
-        Ok(re) => Some((re, 2)),
-        Err(_) => None,
-    }
+/// Совпадение правила в тексте.
+#[derive(Debug, Clone)]
+pub struct RuleMatch<'r> {
+    pub spec: &'r PatternSpec,
+    /// Смещение начала совпадения в байтах.
+    pub start: usize,
+    pub end: usize,
+    /// Всё совпадение регулярки, с именем переменной и кавычками.
+    pub matched: String,
+    /// Сам секрет: `secret_group` или первая непустая группа.
+    pub secret: String,
+    /// Для составного правила — найденные рядом совпадения обязательных правил.
+    pub parts: Vec<RuleMatch<'r>>,
 }
 
-pub static PATTERNS: Lazy<Vec<PatternSpec>> = Lazy::new(|| {
-    let cfg: GitleaksConfig =
-        toml::from_str(RULS_TOML).expect("BUG: не удалось распарсить  ../../config/ruls.toml");
+/// Предфильтр по `keywords`: один проход Aho-Corasick по тексту вместо
+/// запуска всех регулярок подряд.
+#[derive(Debug, Default)]
+pub(crate) struct KeywordFilter {
+    ac: Option<AhoCorasick>,
+    /// Номер слова -> номера правил, в которых оно встречается.
+    owners: Vec<Vec<usize>>,
+    /// Правила без keywords.
+    always: Vec<usize>,
+}
 
-    let mut out: Vec<PatternSpec> = Vec::new();
+impl KeywordFilter {
+    pub(crate) fn new(specs: &[PatternSpec]) -> Self {
+        let mut words: Vec<String> = Vec::new();
+        let mut owners: Vec<Vec<usize>> = Vec::new();
+        let mut always = Vec::new();
 
-    for r in cfg.rules {
-        match compile_with_bigger_limits(&r.regex) {
-            Ok(re) => {
-                out.push(PatternSpec {
-                    re,
-                    name: r.description,
-                    secret_group: r.secret_group,
-                });
+        for (i, spec) in specs.iter().enumerate() {
+            let kws: Vec<String> = spec
+                .keywords
+                .iter()
+                .map(|k| k.trim().to_lowercase())
+                .filter(|k| !k.is_empty())
+                .collect();
+            if kws.is_empty() {
+                always.push(i);
+                continue;
             }
-            Err(e) => {
-                if let Some((re, group_idx)) = build_lightweight_regex_from_keywords(&r.keywords) {
-                    eprintln!(
-                        "[gitleaks] правило '{}' слишком большое: {}. Используем облегчённый regex на базе keywords (secret_group={}).",
-                        r.description, e, group_idx
-                    );
-                    out.push(PatternSpec {
-                        re,
-                        name: format!("{} (lightweight)", r.description),
-                        secret_group: Some(group_idx),
-                    });
-                } else {
-                    eprintln!(
-                        "[gitleaks]  пропустил правило '{}' — не удалось скомпилировать: {} (и нет подходящих keywords)",
-                        r.description, e
-                    );
+            for kw in kws {
+                match words.iter().position(|w| *w == kw) {
+                    Some(j) => owners[j].push(i),
+                    None => {
+                        words.push(kw);
+                        owners.push(vec![i]);
+                    }
                 }
             }
         }
+
+        let ac = match AhoCorasickBuilder::new().ascii_case_insensitive(true).build(&words) {
+            Ok(ac) => Some(ac),
+            Err(e) => {
+                // без автомата просто запускаем все правила
+                eprintln!("[gitleaks] не удалось собрать фильтр keywords: {e}");
+                None
+            }
+        };
+
+        Self { ac, owners, always }
     }
 
-    out
-});
+    /// Какие правила стоит запускать на этом тексте.
+    pub(crate) fn active(&self, text: &str, n_rules: usize) -> Vec<bool> {
+        let Some(ac) = &self.ac else {
+            return vec![true; n_rules];
+        };
+        let mut active = vec![false; n_rules];
+        for &i in &self.always {
+            active[i] = true;
+        }
+        for m in ac.find_overlapping_iter(text) {
+            for &i in &self.owners[m.pattern().as_usize()] {
+                active[i] = true;
+            }
+        }
+        active
+    }
+}
+
+/// Прогнать загруженные правила (`rules::rules()`) по тексту. `location` —
+/// URL ресурса или `URL!файл` для содержимого архива; по нему проверяются
+/// `paths` исключений.
+pub fn scan_text(text: &str, location: &str) -> anyhow::Result<Vec<RuleMatch<'static>>> {
+    Ok(crate::rules::rules()?.scan(text, location))
+}
+
+/// Энтропия Шеннона, бит на символ.
+pub fn shannon_entropy(bytes: &[u8]) -> f64 {
+    if bytes.is_empty() {
+        return 0.0;
+    }
+
+    let mut freq = [0usize; 256];
+    for &b in bytes {
+        freq[b as usize] += 1;
+    }
+
+    let n = bytes.len() as f64;
+    freq.iter()
+        .filter(|&&c| c > 0)
+        .map(|&c| {
+            let p = c as f64 / n;
+            -p * p.log2()
+        })
+        .sum()
+}
 
 // -------- IGNORE: значения (RegexSet) --------
 pub static IGNORE_VALUE_REGEXES: Lazy<RegexSet> = Lazy::new(|| {
-    RegexSet::new(&[
+    RegexSet::new([
         r"(?i)^(true|false|null)$",
         r"^(?i:a+|b+|c+|d+|e+|f+|g+|h+|i+|j+|k+|l+|m+|n+|o+|p+|q+|r+|s+|t+|u+|v+|w+|x+|y+|z+|\*+|\.+)$",
         r#"^\$(\d+|\{\d+\})$"#,
@@ -153,7 +403,7 @@ pub static IGNORE_VALUE_REGEXES: Lazy<RegexSet> = Lazy::new(|| {
 
 // -------- IGNORE: пути/файлы (RegexSet) --------
 pub static IGNORE_PATH_REGEXES: Lazy<RegexSet> = Lazy::new(|| {
-    RegexSet::new(&[
+    RegexSet::new([
         r#"gitleaks\.toml"#,
         r#"(?i)\.(bmp|gif|jpe?g|svg|tiff?)$"#,
         r#"\.(eot|[ot]tf|woff2?)$"#,
@@ -204,3 +454,237 @@ pub fn should_ignore_value(raw: &str) -> bool {
 pub fn should_ignore_path(path_like: &str) -> bool {
     IGNORE_PATH_REGEXES.is_match(path_like)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn allowlist(toml: &str) -> Allowlist {
+        let raw: AllowList = toml::from_str(toml).unwrap();
+        let mut skipped = Vec::new();
+        let a = Allowlist::compile(raw, "test", true, &mut skipped).unwrap();
+        assert!(skipped.is_empty(), "{skipped:?}");
+        a
+    }
+
+    fn spec(re: &str) -> PatternSpec {
+        PatternSpec {
+            re: Regex::new(re).unwrap(),
+            id: "test".to_string(),
+            name: "test".to_string(),
+            tags: Vec::new(),
+            secret_group: None,
+            entropy: None,
+            entropy_group: None,
+            allowlists: Vec::new(),
+            keywords: Vec::new(),
+            origin: "test".to_string(),
+            fallback: None,
+            true_positives: Vec::new(),
+            false_positives: Vec::new(),
+            required: Vec::new(),
+            skip_report: false,
+        }
+    }
+
+    const SECRET: &str = "AKIAEXAMPLE123456789";
+    const MATCHED: &str = "aws_key = \"AKIAEXAMPLE123456789\"";
+    const LINE: &str = "  aws_key = \"AKIAEXAMPLE123456789\" // test fixture";
+
+    #[test]
+    fn allowlist_regex_target_picks_the_compared_text() {
+        let secret = allowlist("regexes = ['''EXAMPLE''']");
+        assert_eq!(secret.regex_target, RegexTarget::Secret);
+        assert!(secret.allows(SECRET, MATCHED, LINE, &[]));
+
+        let on_secret = allowlist("regexes = ['''^aws_key''']");
+        assert!(!on_secret.allows(SECRET, MATCHED, LINE, &[]));
+
+        let on_match = allowlist("regexTarget = \"match\"\nregexes = ['''^aws_key''']");
+        assert_eq!(on_match.regex_target, RegexTarget::Match);
+        assert!(on_match.allows(SECRET, MATCHED, LINE, &[]));
+        assert!(!on_match.allows(SECRET, SECRET, LINE, &[]));
+
+        let on_line = allowlist("regexTarget = \"LINE\"\nregexes = ['''test fixture''']");
+        assert_eq!(on_line.regex_target, RegexTarget::Line);
+        assert!(on_line.allows(SECRET, MATCHED, LINE, &[]));
+        assert!(!on_line.allows(SECRET, MATCHED, MATCHED, &[]));
+    }
+
+    #[test]
+    fn allowlist_condition_or_and() {
+        let or = allowlist("regexes = ['''EXAMPLE''']\nstopwords = [\"nope\"]");
+        assert!(!or.require_all);
+        assert!(or.allows(SECRET, MATCHED, LINE, &[]));
+
+        let and = allowlist("condition = \"AND\"\nregexes = ['''EXAMPLE''']\nstopwords = [\"nope\"]");
+        assert!(and.require_all);
+        assert!(!and.allows(SECRET, MATCHED, LINE, &[]));
+
+        let and_both = allowlist(
+            "condition = \"and\"\nregexes = ['''EXAMPLE''']\npaths = ['''(^|/)test/''']",
+        );
+        assert!(and_both.allows(SECRET, MATCHED, LINE, &["https://ex.com/test/a.js", "test/a.js"]));
+        assert!(!and_both.allows(SECRET, MATCHED, LINE, &["https://ex.com/app.js", "app.js"]));
+
+        // пустое исключение ничего не пропускает
+        assert!(!allowlist("").allows(SECRET, MATCHED, LINE, &[]));
+        assert!(!allowlist("condition = \"AND\"").allows(SECRET, MATCHED, LINE, &[]));
+    }
+
+    #[test]
+    fn allowlist_paths_use_url_path_and_archive_entry() {
+        assert_eq!(
+            path_candidates("https://ex.com/static/app.js?v=1#top"),
+            ["https://ex.com/static/app.js?v=1#top", "static/app.js"]
+        );
+        assert_eq!(
+            path_candidates("https://ex.com/dl/src.zip!config/test/keys.env"),
+            ["https://ex.com/dl/src.zip!config/test/keys.env", "dl/src.zip", "config/test/keys.env"]
+        );
+        assert_eq!(path_candidates("https://ex.com/"), ["https://ex.com/"]);
+        assert_eq!(path_candidates("/tmp/site/a.js"), ["/tmp/site/a.js"]);
+
+        let a = allowlist("paths = ['''^config/test/''', '''\\.min\\.js$''']");
+        let allowed = |location: &str| a.allows(SECRET, MATCHED, LINE, &path_candidates(location));
+        assert!(allowed("https://ex.com/dl/src.zip!config/test/keys.env"));
+        assert!(allowed("https://ex.com/static/vendor.min.js"));
+        assert!(!allowed("https://ex.com/static/app.js"));
+        // `^` привязан к пути URL, а не к адресу целиком
+        assert!(allowed("https://ex.com/config/test/keys.env"));
+        assert!(!allowed("https://ex.com/dl/src.zip!src/config/test/keys.env"));
+    }
+
+    #[test]
+    fn allowlist_stopwords_are_case_insensitive_substrings_of_the_secret() {
+        let a = allowlist("stopwords = [\"  Example \", \"\"]");
+        assert_eq!(a.stopwords, ["example"]);
+        assert!(a.allows(SECRET, MATCHED, LINE, &[]));
+        assert!(!a.allows("AKIAREAL1234567890AB", "example = AKIAREAL1234567890AB", LINE, &[]));
+
+        let only = Allowlist::stopwords_only(vec!["DUMMY".to_string()]);
+        assert!(only.allows("xxdummyxx", "", "", &[]));
+        assert!(!only.allows("xxdumxx", "dummy", "dummy", &[]));
+    }
+
+    #[test]
+    fn unknown_regex_target_is_an_error_only_in_strict_mode() {
+        let raw = || -> AllowList { toml::from_str("regexTarget = \"commit\"").unwrap() };
+        let mut skipped = Vec::new();
+        assert!(Allowlist::compile(raw(), "r", true, &mut skipped).is_err());
+        let a = Allowlist::compile(raw(), "r", false, &mut skipped).unwrap();
+        assert_eq!(a.regex_target, RegexTarget::Secret);
+        assert_eq!(skipped.len(), 1);
+    }
+
+    #[test]
+    fn shannon_entropy_bits_per_char() {
+        assert_eq!(shannon_entropy(b""), 0.0);
+        assert_eq!(shannon_entropy(b"aaaaaaaa"), 0.0);
+        assert!((shannon_entropy(b"abababab") - 1.0).abs() < 1e-9);
+        assert!((shannon_entropy(b"0123456789abcdef") - 4.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn entropy_threshold_rejects_low_and_keeps_high() {
+        let mut s = spec(r"key=(\w+)");
+        s.entropy = Some(3.5);
+        let low = s.re.captures("key=aaaabbbbaaaabbbb").unwrap();
+        let high = s.re.captures("key=Zq8vK2pLm7Xw4RtY1nB5").unwrap();
+        assert!(!s.entropy_ok(&low));
+        assert!(s.entropy_ok(&high));
+
+        // порог включительный: ровно 4 бита на 16 разных символах проходят
+        s.entropy = Some(4.0);
+        assert!(s.entropy_ok(&s.re.captures("key=0123456789abcdef").unwrap()));
+
+        s.entropy = None;
+        assert!(s.entropy_ok(&low));
+    }
+
+    #[test]
+    fn entropy_is_measured_on_entropy_group_or_secret() {
+        // группа 1 — разнообразный префикс, группа 2 — однообразный секрет
+        let mut s = spec(r"(Zq8vK2pLm7Xw4RtY)_(a{16})");
+        let cap = s.re.captures("Zq8vK2pLm7Xw4RtY_aaaaaaaaaaaaaaaa").unwrap();
+        s.entropy = Some(3.0);
+
+        s.secret_group = Some(2);
+        assert_eq!(s.entropy_target(&cap), "aaaaaaaaaaaaaaaa");
+        assert!(!s.entropy_ok(&cap));
+
+        s.entropy_group = Some(1);
+        assert_eq!(s.entropy_target(&cap), "Zq8vK2pLm7Xw4RtY");
+        assert!(s.entropy_ok(&cap));
+
+        // несуществующая группа — считаем по секрету
+        s.entropy_group = Some(7);
+        assert_eq!(s.entropy_target(&cap), "aaaaaaaaaaaaaaaa");
+    }
+
+    #[test]
+    fn secret_of_prefers_secret_group_then_first_nonempty_then_whole_match() {
+        let mut s = spec(r"(?:(user)=)?(\w*):(\w+)");
+        let cap = s.re.captures(":hunter22").unwrap();
+
+        // группа 1 не участвовала, группа 2 пустая — берётся первая непустая
+        assert_eq!(s.secret_of(&cap), "hunter22");
+
+        let cap = s.re.captures("user=admin:hunter22").unwrap();
+        assert_eq!(s.secret_of(&cap), "user");
+
+        s.secret_group = Some(3);
+        assert_eq!(s.secret_of(&cap), "hunter22");
+
+        // номер за пределами групп — как без secretGroup
+        s.secret_group = Some(9);
+        assert_eq!(s.secret_of(&cap), "user");
+
+        // группа есть в regex, но не участвовала в совпадении — тоже запасной путь
+        s.secret_group = Some(1);
+        assert_eq!(s.secret_of(&s.re.captures("admin:hunter22").unwrap()), "admin");
+
+        let whole = spec(r"sk_live_[0-9a-z]{8}");
+        let cap = whole.re.captures("x sk_live_0123abcd y").unwrap();
+        assert_eq!(whole.secret_of(&cap), "sk_live_0123abcd");
+
+        // все группы пустые — тоже всё совпадение
+        let empty = spec(r"tok(\d*)en");
+        assert_eq!(empty.secret_of(&empty.re.captures("token").unwrap()), "token");
+    }
+
+    fn with_keywords(words: &[&str]) -> PatternSpec {
+        let mut s = spec(r"\w+");
+        s.keywords = words.iter().map(|w| w.to_string()).collect();
+        s
+    }
+
+    #[test]
+    fn keyword_filter_runs_only_rules_with_a_keyword_in_text() {
+        let specs = [
+            with_keywords(&["ghp_", "github_pat_"]),
+            with_keywords(&["AKIA"]),
+            with_keywords(&[]),
+            with_keywords(&["  ", ""]),
+            with_keywords(&["akia", "xoxb-"]),
+        ];
+        let filter = KeywordFilter::new(&specs);
+        let n = specs.len();
+
+        // без keywords (и с одними пустыми) — всегда
+        assert_eq!(filter.active("nothing here", n), [false, false, true, true, false]);
+        // регистр ASCII не важен, общее слово включает оба правила
+        assert_eq!(filter.active("id = akiaXYZ", n), [false, true, true, true, true]);
+        assert_eq!(filter.active("GHP_abc and XOXB-1", n), [true, false, true, true, true]);
+        // слова внутри других слов тоже считаются
+        assert_eq!(filter.active("my_github_pat_x", n), [true, false, true, true, false]);
+        assert_eq!(filter.active("", n), [false, false, true, true, false]);
+    }
+
+    #[test]
+    fn keyword_filter_without_keywords_runs_everything() {
+        let specs = [with_keywords(&[]), with_keywords(&[])];
+        assert_eq!(KeywordFilter::new(&specs).active("x", 2), [true, true]);
+        assert!(KeywordFilter::new(&[]).active("x", 0).is_empty());
+    }
+}
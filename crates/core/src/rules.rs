@@ -0,0 +1,842 @@
+use crate::patterns::{
+    Allowlist, KeywordFilter, LineIndex, PatternSpec, Required, RuleMatch, gap, line_around,
+    should_ignore_value,
+};
+
+use anyhow::{Context, Result as AnyResult, bail, ensure};
+use once_cell::sync::OnceCell;
+use regex::{Regex, RegexBuilder};
+use serde::Deserialize;
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+const RULS_TOML: &str = config::RULS_TOML;
+
+/// Как в gitleaks: не больше двух уровней `[extend] path` над файлом из `--rules`.
+const MAX_EXTEND_DEPTH: usize = 2;
+
+#[derive(Debug, Deserialize)]
+struct GitleaksConfig {
+    #[serde(default)]
+    extend: Option<Extend>,
+
+    #[serde(default)]
+    rules: Vec<GitleaksRule>,
+
+    /// Глобальные исключения, действуют на все правила.
+    #[serde(default)]
+    allowlist: Option<AllowList>,
+
+    #[serde(default)]
+    allowlists: Vec<AllowList>,
+}
+
+/// Секция `[extend]` пользовательского файла правил.
+#[derive(Debug, Default, Deserialize)]
+pub struct Extend {
+    /// `false` — не подключать встроенный ruls.toml.
+    #[serde(default, rename = "useDefault")]
+    pub use_default: Option<bool>,
+
+    /// Базовый файл правил, путь относительно текущего.
+    #[serde(default)]
+    pub path: Option<PathBuf>,
+
+    /// `id` правил, которые нужно выключить.
+    #[serde(default, rename = "disabledRules")]
+    pub disabled_rules: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GitleaksRule {
+    pub id: String,
+
+    #[serde(default)]
+    pub description: String,
+
+    pub regex: String,
+
+    #[serde(default)]
+    pub report: Option<String>, // иногда есть короткий отчёт
+
+    #[serde(default)]
+    pub tags: Vec<String>, // например ["key", "AWS"]
+
+    #[serde(default)]
+    pub entropy: Option<f64>, // минимальная энтропия
+
+    #[serde(rename = "entropyGroup")]
+    pub entropy_group: Option<usize>,
+
+    #[serde(rename = "secretGroup")]
+    pub secret_group: Option<usize>,
+
+    #[serde(default)]
+    pub keywords: Vec<String>,
+
+    #[serde(default)]
+    pub stopwords: Vec<String>, // слова, при которых можно игнорить
+
+    #[serde(default)]
+    pub allowlists: Vec<AllowList>,
+
+    /// Старый формат: одна таблица `[rules.allowlist]`.
+    #[serde(default)]
+    pub allowlist: Option<AllowList>,
+
+    /// Примеры для `rules test`: на этих строках правило должно сработать…
+    #[serde(default, rename = "truePositives")]
+    pub true_positives: Vec<String>,
+
+    /// …а на этих — нет.
+    #[serde(default, rename = "falsePositives")]
+    pub false_positives: Vec<String>,
+
+    /// Составное правило: `[[rules.required]]`.
+    #[serde(default)]
+    pub required: Vec<RequiredRule>,
+
+    /// Правило нужно только как часть составных, отдельно не сообщать.
+    #[serde(default, rename = "skipReport")]
+    pub skip_report: bool,
+}
+
+/// Обязательная часть составного правила.
+#[derive(Debug, Deserialize)]
+pub struct RequiredRule {
+    pub id: String,
+
+    #[serde(default, rename = "withinLines")]
+    pub within_lines: Option<usize>,
+
+    #[serde(default, rename = "withinColumns")]
+    pub within_columns: Option<usize>,
+
+    /// Не из gitleaks: не больше N байт между совпадениями.
+    #[serde(default, rename = "withinBytes")]
+    pub within_bytes: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AllowList {
+    #[serde(default)]
+    pub condition: Option<String>, // "OR" (по умолчанию) или "AND"
+
+    #[serde(default, rename = "regexTarget")]
+    pub regex_target: Option<String>, // "secret" (по умолчанию), "match", "line"
+
+    #[serde(default)]
+    pub regexes: Vec<String>,
+
+    #[serde(default)]
+    pub paths: Vec<String>,
+
+    #[serde(default)]
+    pub commits: Vec<String>,
+
+    #[serde(default)]
+    pub files: Vec<String>,
+
+    #[serde(default)]
+    pub stopwords: Vec<String>,
+}
+
+pub(crate) fn compile_with_bigger_limits(pat: &str) -> Result<Regex, regex::Error> {
+    RegexBuilder::new(pat)
+        .size_limit(64 * 1024 * 1024) // 64 MiB на таблицы
+        .dfa_size_limit(64 * 1024 * 1024) // 64 MiB на DFA
+        .build()
+}
+
+fn build_lightweight_regex_from_keywords(keywords: &[String]) -> Option<(Regex, usize)> {
+    if keywords.is_empty() {
+        return None;
+    }
+    let alts: String = keywords
+        .iter()
+        .filter(|s| !s.trim().is_empty())
+        .map(|s| regex::escape(s))
+        .collect::<Vec<_>>()
+        .join("|");
+
+    if alts.is_empty() {
+        return None;
+    }
+
+    let pat = format!(
+        r#"(?i)\b(?:{})(?:\W{{0,20}}[:=]\W{{0,20}}|\W{{1,20}})?(['\"]?)([A-Za-z0-9_\-]{{20,}})(\1)?"#,
+        alts
+    );
+    match compile_with_bigger_limits(&pat) {
+        Ok(re) => Some((re, 2)),
+        Err(_) => None,
+    }
+}
+
+/// Один разобранный файл правил.
+struct RuleSource {
+    /// Откуда взят: путь файла или встроенный ruls.toml.
+    origin: String,
+    /// Пользовательские файлы проверяются строго, встроенный — с предупреждениями.
+    strict: bool,
+    cfg: GitleaksConfig,
+}
+
+impl RuleSource {
+    fn builtin() -> AnyResult<Self> {
+        let cfg = toml::from_str(RULS_TOML).context("разбор встроенного ruls.toml")?;
+        Ok(Self {
+            origin: "ruls.toml".to_string(),
+            strict: false,
+            cfg,
+        })
+    }
+
+    /// Файл и, раньше него, базовые файлы из `[extend] path`.
+    fn read_chain(path: &Path, depth: usize, out: &mut Vec<RuleSource>) -> AnyResult<()> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("чтение правил {}", path.display()))?;
+        let cfg: GitleaksConfig = toml::from_str(&text)
+            .with_context(|| format!("разбор правил {}", path.display()))?;
+
+        if let Some(base) = cfg.extend.as_ref().and_then(|e| e.path.as_ref()) {
+            ensure!(
+                depth < MAX_EXTEND_DEPTH,
+                "{}: [extend] path глубже {MAX_EXTEND_DEPTH} уровней",
+                path.display()
+            );
+            let base = path.parent().unwrap_or(Path::new(".")).join(base);
+            Self::read_chain(&base, depth + 1, out)?;
+        }
+
+        out.push(Self {
+            origin: path.display().to_string(),
+            strict: true,
+            cfg,
+        });
+        Ok(())
+    }
+}
+
+/// Что пошло не так при загрузке правила, но не помешало скану.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoteKind {
+    /// Regex не собрался, работает облегчённый вариант по keywords.
+    Lightweight,
+    /// Правило выброшено: regex не собрался, а keywords нет.
+    Skipped,
+    /// Часть исключений правила не работает.
+    Allowlist,
+    /// `secretGroup` / `entropyGroup` больше числа групп в regex.
+    Group,
+    /// `disabledRules` ссылается на неизвестное правило.
+    UnknownDisabled,
+    /// Составное правило выключено: в `required` неизвестное правило.
+    Required,
+}
+
+impl NoteKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NoteKind::Lightweight => "lightweight",
+            NoteKind::Skipped => "skipped",
+            NoteKind::Allowlist => "allowlist",
+            NoteKind::Group => "group",
+            NoteKind::UnknownDisabled => "unknown-disabled",
+            NoteKind::Required => "required",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RuleNote {
+    pub rule_id: String,
+    pub origin: String,
+    pub kind: NoteKind,
+    pub message: String,
+}
+
+/// Провал примера из `truePositives` / `falsePositives`.
+#[derive(Debug, Clone)]
+pub struct ExampleFailure {
+    pub rule_id: String,
+    pub example: String,
+    /// `true` — пример из `truePositives`, правило на нём не сработало.
+    pub expected_match: bool,
+    pub reason: String,
+}
+
+/// Набор правил для скана: встроенный ruls.toml плюс файлы `--rules`.
+#[derive(Debug, Default)]
+pub struct RuleSet {
+    pub rules: Vec<PatternSpec>,
+    /// Замечания загрузки, для `rules lint`.
+    pub notes: Vec<RuleNote>,
+    filter: KeywordFilter,
+}
+
+impl RuleSet {
+    /// Только встроенные правила.
+    pub fn builtin() -> AnyResult<Self> {
+        Self::load(&[])
+    }
+
+    /// Встроенные правила и пользовательские файлы, в порядке аргументов.
+    ///
+    /// Правило с уже известным `id` заменяет прежнее, `[extend] disabledRules`
+    /// выключает правила по `id`, а `[extend] useDefault = false` в любом из
+    /// файлов отключает встроенный набор.
+    pub fn load(files: &[PathBuf]) -> AnyResult<Self> {
+        let mut user = Vec::new();
+        for f in files {
+            RuleSource::read_chain(f, 0, &mut user)?;
+        }
+
+        let use_default = user
+            .iter()
+            .all(|s| s.cfg.extend.as_ref().and_then(|e| e.use_default) != Some(false));
+        let mut sources = Vec::with_capacity(user.len() + 1);
+        if use_default {
+            sources.push(RuleSource::builtin()?);
+        }
+        sources.extend(user);
+
+        let mut rules: Vec<(GitleaksRule, usize)> = Vec::new();
+        let mut by_id: HashMap<String, usize> = HashMap::new();
+        let mut global: Vec<Allowlist> = Vec::new();
+        let mut disabled: Vec<(String, usize)> = Vec::new();
+        let mut notes = Vec::new();
+
+        for (si, src) in sources.iter_mut().enumerate() {
+            let cfg = &mut src.cfg;
+            for a in cfg.allowlist.take().into_iter().chain(cfg.allowlists.drain(..)) {
+                let mut skipped = Vec::new();
+                let a = Allowlist::compile(a, "global", src.strict, &mut skipped)
+                    .with_context(|| src.origin.clone())?;
+                notes.extend(skipped.into_iter().map(|message| RuleNote {
+                    rule_id: "global".to_string(),
+                    origin: src.origin.clone(),
+                    kind: NoteKind::Allowlist,
+                    message,
+                }));
+                global.push(a);
+            }
+            if let Some(ext) = cfg.extend.as_mut() {
+                disabled.extend(ext.disabled_rules.drain(..).map(|id| (id, si)));
+            }
+            for r in cfg.rules.drain(..) {
+                ensure!(!r.id.trim().is_empty(), "{}: правило без id", src.origin);
+                match by_id.get(&r.id) {
+                    Some(&i) => rules[i] = (r, si),
+                    None => {
+                        by_id.insert(r.id.clone(), rules.len());
+                        rules.push((r, si));
+                    }
+                }
+            }
+        }
+
+        let mut off = HashSet::new();
+        for (id, si) in disabled {
+            if !by_id.contains_key(&id) {
+                notes.push(RuleNote {
+                    rule_id: id.clone(),
+                    origin: sources[si].origin.clone(),
+                    kind: NoteKind::UnknownDisabled,
+                    message: "disabledRules: такого правила нет".to_string(),
+                });
+            }
+            off.insert(id);
+        }
+
+        let mut specs = Vec::with_capacity(rules.len());
+        let mut strict = Vec::with_capacity(rules.len());
+        for (r, si) in rules {
+            if off.contains(&r.id) {
+                continue;
+            }
+            let src = &sources[si];
+            let spec = compile_rule(r, &src.origin, src.strict, &global, &mut notes)
+                .with_context(|| src.origin.clone())?;
+            if let Some(spec) = spec {
+                specs.push(spec);
+                strict.push(src.strict);
+            }
+        }
+        resolve_required(&mut specs, &strict, &mut notes)?;
+
+        let filter = KeywordFilter::new(&specs);
+        Ok(Self {
+            rules: specs,
+            notes,
+            filter,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Прогнать правила по тексту.
+    ///
+    /// Составное правило срабатывает, только если для каждой части из
+    /// `required` рядом нашлось совпадение; ближайшие такие совпадения попадают
+    /// в `parts` и отдельно уже не сообщаются.
+    pub fn scan<'r>(&'r self, text: &str, location: &str) -> Vec<RuleMatch<'r>> {
+        let active = self.filter.active(text, self.rules.len());
+        let mut by_rule: Vec<Vec<RuleMatch<'r>>> = Vec::with_capacity(self.rules.len());
+        for (spec, on) in self.rules.iter().zip(active) {
+            let mut found = Vec::new();
+            if on {
+                scan_rule(spec, text, location, &mut found);
+            }
+            by_rule.push(found);
+        }
+
+        let mut composite: Vec<Vec<RuleMatch<'r>>> = vec![Vec::new(); self.rules.len()];
+        let mut used: HashSet<(usize, usize)> = HashSet::new();
+        let mut lines = None;
+        for (i, spec) in self.rules.iter().enumerate() {
+            if spec.required.is_empty() || by_rule[i].is_empty() {
+                continue;
+            }
+            let lines = lines.get_or_insert_with(|| LineIndex::new(text));
+            for m in &by_rule[i] {
+                let picked: Option<Vec<(usize, usize)>> = spec
+                    .required
+                    .iter()
+                    .map(|req| nearest_part(req, m, &by_rule[req.index], lines).map(|j| (req.index, j)))
+                    .collect();
+                let Some(picked) = picked else { continue };
+                let mut full = m.clone();
+                full.parts = picked.iter().map(|&(r, j)| by_rule[r][j].clone()).collect();
+                used.extend(picked);
+                composite[i].push(full);
+            }
+        }
+
+        let mut out = Vec::new();
+        for (i, (spec, found)) in self.rules.iter().zip(by_rule).enumerate() {
+            if !spec.required.is_empty() {
+                out.append(&mut composite[i]);
+            } else if !spec.skip_report {
+                out.extend(
+                    found
+                        .into_iter()
+                        .enumerate()
+                        .filter(|(j, _)| !used.contains(&(i, *j)))
+                        .map(|(_, m)| m),
+                );
+            }
+        }
+        out
+    }
+
+    /// Прогнать примеры `truePositives` / `falsePositives` каждого правила.
+    /// Возвращает число проверенных примеров и провалы.
+    pub fn test_examples(&self) -> (usize, Vec<ExampleFailure>) {
+        let mut total = 0;
+        let mut failures = Vec::new();
+
+        for spec in &self.rules {
+            for (examples, expected_match) in
+                [(&spec.true_positives, true), (&spec.false_positives, false)]
+            {
+                for example in examples {
+                    total += 1;
+                    let mut found = Vec::new();
+                    if spec.required.is_empty() {
+                        scan_rule(spec, example, "", &mut found);
+                    } else {
+                        // частям нужны другие правила — гоняем весь набор
+                        found.extend(
+                            self.scan(example, "")
+                                .into_iter()
+                                .filter(|m| std::ptr::eq(m.spec, spec)),
+                        );
+                    }
+                    let has_keyword = spec.keywords.is_empty() || {
+                        let lower = example.to_lowercase();
+                        spec.keywords.iter().any(|k| lower.contains(&k.to_lowercase()))
+                    };
+
+                    let reason = match (expected_match, found.first()) {
+                        (true, None) => Some("не сработало".to_string()),
+                        (true, Some(_)) if !has_keyword => {
+                            Some("совпало, но в примере нет ни одного keyword".to_string())
+                        }
+                        (false, Some(m)) => Some(format!("сработало: {}", m.secret)),
+                        _ => None,
+                    };
+                    if let Some(reason) = reason {
+                        failures.push(ExampleFailure {
+                            rule_id: spec.id.clone(),
+                            example: example.clone(),
+                            expected_match,
+                            reason,
+                        });
+                    }
+                }
+            }
+        }
+
+        (total, failures)
+    }
+}
+
+/// Совпадения одного правила, прошедшие фильтры значений, энтропии и исключений.
+fn scan_rule<'r>(spec: &'r PatternSpec, text: &str, location: &str, out: &mut Vec<RuleMatch<'r>>) {
+    for cap in spec.re.captures_iter(text) {
+        let m = match cap.get(0) {
+            Some(v) => v,
+            None => continue,
+        };
+
+        let secret = spec.secret_of(&cap);
+        if should_ignore_value(secret) || !spec.entropy_ok(&cap) {
+            continue;
+        }
+
+        let line = line_around(text, m.start(), m.end());
+        if spec.allowed(secret, m.as_str(), line, location) {
+            continue;
+        }
+
+        out.push(RuleMatch {
+            spec,
+            start: m.start(),
+            end: m.end(),
+            matched: m.as_str().to_string(),
+            secret: secret.to_string(),
+            parts: Vec::new(),
+        });
+    }
+}
+
+/// Ближайшее к `main` совпадение части `req`, подходящее по расстоянию.
+fn nearest_part(
+    req: &Required,
+    main: &RuleMatch,
+    candidates: &[RuleMatch],
+    lines: &LineIndex,
+) -> Option<usize> {
+    candidates
+        .iter()
+        .enumerate()
+        .filter(|(_, c)| (c.start, c.end) != (main.start, main.end))
+        .filter(|(_, c)| req.near(main, c, lines))
+        .min_by_key(|(_, c)| gap(main, c))
+        .map(|(j, _)| j)
+}
+
+/// Проставить номера правил в `required`. Составное правило с неизвестной
+/// частью выключается (во встроенных) или останавливает загрузку; выключение
+/// может оставить без части другое составное правило, поэтому по кругу.
+fn resolve_required(
+    specs: &mut Vec<PatternSpec>,
+    strict: &[bool],
+    notes: &mut Vec<RuleNote>,
+) -> AnyResult<()> {
+    let mut keep: Vec<bool> = vec![true; specs.len()];
+    loop {
+        let known: HashSet<&str> = specs
+            .iter()
+            .zip(&keep)
+            .filter(|(_, k)| **k)
+            .map(|(s, _)| s.id.as_str())
+            .collect();
+        let mut dropped = Vec::new();
+        for (i, spec) in specs.iter().enumerate().filter(|(i, _)| keep[*i]) {
+            if let Some(req) = spec.required.iter().find(|r| !known.contains(r.id.as_str())) {
+                ensure!(
+                    !strict[i],
+                    "{}: правило '{}': required ссылается на неизвестное правило '{}'",
+                    spec.origin,
+                    spec.id,
+                    req.id
+                );
+                notes.push(RuleNote {
+                    rule_id: spec.id.clone(),
+                    origin: spec.origin.clone(),
+                    kind: NoteKind::Required,
+                    message: format!("required: нет правила '{}', правило выключено", req.id),
+                });
+                dropped.push(i);
+            }
+        }
+        if dropped.is_empty() {
+            break;
+        }
+        for i in dropped {
+            keep[i] = false;
+        }
+    }
+
+    let mut keep = keep.into_iter();
+    specs.retain(|_| keep.next().unwrap_or(true));
+    let index: HashMap<String, usize> =
+        specs.iter().enumerate().map(|(i, s)| (s.id.clone(), i)).collect();
+    for spec in specs.iter_mut() {
+        for req in &mut spec.required {
+            req.index = index[&req.id];
+        }
+    }
+    Ok(())
+}
+
+/// Скомпилировать правило; `None` — встроенное правило пропущено.
+fn compile_rule(
+    mut r: GitleaksRule,
+    origin: &str,
+    strict: bool,
+    global: &[Allowlist],
+    notes: &mut Vec<RuleNote>,
+) -> AnyResult<Option<PatternSpec>> {
+    let mut note = |kind: NoteKind, message: String| {
+        notes.push(RuleNote {
+            rule_id: r.id.clone(),
+            origin: origin.to_string(),
+            kind,
+            message,
+        })
+    };
+
+    let mut allowlists = Vec::new();
+    let mut skipped = Vec::new();
+    for a in r.allowlist.take().into_iter().chain(r.allowlists.drain(..)) {
+        allowlists.push(Allowlist::compile(a, &r.id, strict, &mut skipped)?);
+    }
+    for message in skipped {
+        note(NoteKind::Allowlist, message);
+    }
+    if !r.stopwords.is_empty() {
+        allowlists.push(Allowlist::stopwords_only(std::mem::take(&mut r.stopwords)));
+    }
+    allowlists.extend(global.iter().cloned());
+
+    let description = if r.description.trim().is_empty() {
+        r.id.clone()
+    } else {
+        r.description.clone()
+    };
+
+    let (re, name, secret_group, entropy_group, fallback) = match compile_with_bigger_limits(&r.regex) {
+        Ok(re) => (re, description, r.secret_group, r.entropy_group, None),
+        Err(e) => {
+            if let Some((re, group_idx)) = build_lightweight_regex_from_keywords(&r.keywords) {
+                let reason = e.to_string();
+                note(
+                    NoteKind::Lightweight,
+                    format!("облегчённый regex по keywords (secret_group={group_idx}): {reason}"),
+                );
+                (re, format!("{description} (lightweight)"), Some(group_idx), None, Some(reason))
+            } else if strict {
+                bail!("правило '{}': regex не компилируется: {e}", r.id);
+            } else {
+                note(NoteKind::Skipped, format!("regex не компилируется, keywords нет: {e}"));
+                return Ok(None);
+            }
+        }
+    };
+
+    let groups = re.captures_len() - 1;
+    for (key, group) in [("secretGroup", secret_group), ("entropyGroup", entropy_group)] {
+        if let Some(g) = group.filter(|&g| g > groups) {
+            ensure!(!strict, "правило '{}': {key} = {g}, а групп в regex {groups}", r.id);
+            note(NoteKind::Group, format!("{key} = {g}, а групп в regex {groups}"));
+        }
+    }
+
+    Ok(Some(PatternSpec {
+        re,
+        id: r.id,
+        name,
+        tags: r.tags,
+        secret_group,
+        entropy: r.entropy,
+        entropy_group,
+        allowlists,
+        keywords: r.keywords,
+        origin: origin.to_string(),
+        fallback,
+        true_positives: r.true_positives,
+        false_positives: r.false_positives,
+        required: r
+            .required
+            .into_iter()
+            .map(|q| Required {
+                id: q.id,
+                index: 0,
+                within_lines: q.within_lines,
+                within_columns: q.within_columns,
+                within_bytes: q.within_bytes,
+            })
+            .collect(),
+        skip_report: r.skip_report,
+    }))
+}
+
+static RULES: OnceCell<RuleSet> = OnceCell::new();
+
+/// Загрузить правила для всего процесса (встроенные + `files`). Повторный
+/// вызов без файлов возвращает уже загруженный набор; с файлами — ошибка,
+/// чтобы `--rules` не потерялись молча.
+pub fn init_rules(files: &[PathBuf]) -> AnyResult<&'static RuleSet> {
+    let mut loaded = false;
+    let set = RULES.get_or_try_init(|| -> AnyResult<RuleSet> {
+        let set = RuleSet::load(files)?;
+        announce(&set);
+        loaded = true;
+        Ok(set)
+    })?;
+    ensure!(loaded || files.is_empty(), "правила уже загружены, файлы --rules не применены");
+    Ok(set)
+}
+
+/// Текущий набор правил; без `init_rules` — только встроенные. Ошибка
+/// разбора встроенных правил возвращается, а не заменяется пустым набором.
+pub fn rules() -> AnyResult<&'static RuleSet> {
+    RULES.get_or_try_init(|| {
+        let set = RuleSet::builtin()?;
+        announce(&set);
+        Ok(set)
+    })
+}
+
+fn announce(set: &RuleSet) {
+    if !set.notes.is_empty() {
+        eprintln!(
+            "[gitleaks] замечаний к правилам: {} (подробности: `rules lint`)",
+            set.notes.len()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Пустая папка под файлы правил теста.
+    fn rules_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("webhound-rules-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn write(dir: &Path, name: &str, toml: &str) -> PathBuf {
+        let path = dir.join(name);
+        fs::write(&path, toml).unwrap();
+        path
+    }
+
+    fn find<'a>(set: &'a RuleSet, id: &str) -> Option<&'a PatternSpec> {
+        set.rules.iter().find(|r| r.id == id)
+    }
+
+    /// Файл правил без встроенного набора: `id` → regex.
+    fn own_rules(rules: &[(&str, &str)]) -> String {
+        let mut toml = "[extend]\nuseDefault = false\n".to_string();
+        for (id, re) in rules {
+            toml.push_str(&format!("[[rules]]\nid = \"{id}\"\nregex = \"{re}\"\n"));
+        }
+        toml
+    }
+
+    #[test]
+    fn same_id_replaces_rule_in_place() {
+        let dir = rules_dir("replace");
+        let base = write(&dir, "base.toml", &own_rules(&[("a", "a_[0-9]+"), ("b", "b_[0-9]+")]));
+        let over = write(&dir, "over.toml", &own_rules(&[("a", "own_[0-9]+"), ("c", "c_[0-9]+")]));
+
+        let set = RuleSet::load(&[base, over.clone()]).unwrap();
+        let ids: Vec<_> = set.rules.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids, ["a", "b", "c"]);
+        let spec = find(&set, "a").unwrap();
+        assert_eq!(spec.re.as_str(), "own_[0-9]+");
+        assert_eq!(spec.origin, over.display().to_string());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn disabled_rules_are_dropped_and_unknown_ids_noted() {
+        let dir = rules_dir("disabled");
+        let base = write(&dir, "base.toml", &own_rules(&[("a", "a_[0-9]+"), ("b", "b_[0-9]+")]));
+        let off = write(
+            &dir,
+            "off.toml",
+            "[extend]\ndisabledRules = [\"a\", \"no-such-rule\"]\n",
+        );
+
+        let set = RuleSet::load(&[base, off]).unwrap();
+        assert!(find(&set, "a").is_none());
+        assert!(find(&set, "b").is_some());
+        let unknown: Vec<_> = set
+            .notes
+            .iter()
+            .filter(|n| n.kind == NoteKind::UnknownDisabled)
+            .map(|n| n.rule_id.as_str())
+            .collect();
+        assert_eq!(unknown, ["no-such-rule"]);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn use_default_false_drops_builtin_rules() {
+        let dir = rules_dir("no-default");
+        let own = write(&dir, "own.toml", "[[rules]]\nid = \"own\"\nregex = \"own_[0-9]{8}\"\n");
+        let off = write(&dir, "off.toml", &own_rules(&[]));
+
+        // достаточно одного файла с useDefault = false, порядок не важен
+        let set = RuleSet::load(&[own, off]).unwrap();
+        let ids: Vec<_> = set.rules.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids, ["own"]);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn extend_chain_is_limited_in_depth() {
+        let dir = rules_dir("extend");
+        let rule = |re: &str| format!("[[rules]]\nid = \"x\"\nregex = \"{re}\"\n");
+        write(&dir, "c.toml", &format!("[extend]\nuseDefault = false\n{}", rule("c_[0-9]+")));
+        write(&dir, "b.toml", "[extend]\npath = \"c.toml\"\n");
+        let a = write(&dir, "a.toml", &format!("[extend]\npath = \"b.toml\"\n{}", rule("a_[0-9]+")));
+
+        // два уровня: базовые файлы читаются раньше, a.toml переопределяет x
+        let set = RuleSet::load(&[a.clone()]).unwrap();
+        assert_eq!(set.len(), 1);
+        assert_eq!(set.rules[0].re.as_str(), "a_[0-9]+");
+
+        write(&dir, "c.toml", &format!("[extend]\npath = \"d.toml\"\n{}", rule("c_[0-9]+")));
+        write(&dir, "d.toml", &rule("d_[0-9]+"));
+        let err = RuleSet::load(&[a]).unwrap_err();
+        assert!(format!("{err:#}").contains("глубже"), "{err:#}");
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn init_rules_refuses_files_once_loaded() {
+        let dir = rules_dir("init");
+        let file = write(&dir, "own.toml", "[[rules]]\nid = \"own\"\nregex = \"own_[0-9]{8}\"\n");
+
+        let loaded = rules().unwrap();
+        assert!(init_rules(&[file]).is_err());
+        assert!(std::ptr::eq(init_rules(&[]).unwrap(), loaded));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn builtin_examples_pass() {
+        let set = RuleSet::builtin().unwrap();
+        let (total, failures) = set.test_examples();
+        assert!(total >= 21, "примеров: {total}");
+        assert!(failures.is_empty(), "{failures:#?}");
+    }
+}
@@ -0,0 +1,206 @@
+use crate::scope::Scope;
+
+use anyhow::{Context, Result as AnyResult, anyhow};
+use std::{
+    fs,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use url::Url;
+
+const ARCHIVE_HOST: &str = "web.archive.org";
+
+/// Cookie из файла в формате Netscape (`cookies.txt`).
+#[derive(Debug, Clone)]
+pub struct Cookie {
+    pub domain: String,
+    pub include_subdomains: bool,
+    pub path: String,
+    pub secure: bool,
+    pub http_only: bool,
+    pub expires: i64,
+    pub name: String,
+    pub value: String,
+}
+
+impl Cookie {
+    /// Срок истёк; сессионные cookies (`expires` = 0) не истекают.
+    pub fn is_expired(&self, now: i64) -> bool {
+        self.expires > 0 && self.expires <= now
+    }
+
+    fn matches(&self, url: &Url) -> bool {
+        let host = match url.host_str() {
+            Some(h) => h.to_ascii_lowercase(),
+            None => return false,
+        };
+        let domain = self.domain.trim_start_matches('.').to_ascii_lowercase();
+
+        let host_ok = host == domain
+            || (self.include_subdomains && host.ends_with(&format!(".{domain}")));
+        let secure_ok = !self.secure || url.scheme() == "https";
+
+        host_ok && secure_ok && url.path().starts_with(self.path.as_str())
+    }
+}
+
+/// Учётные данные для сканирования за логином.
+#[derive(Debug, Clone, Default)]
+pub struct Credentials {
+    pub headers: Vec<(String, String)>,
+    pub cookies: Vec<Cookie>,
+    pub bearer: Option<String>,
+}
+
+impl Credentials {
+    pub fn is_empty(&self) -> bool {
+        self.headers.is_empty() && self.cookies.is_empty() && self.bearer.is_none()
+    }
+
+    /// Добавить заголовок в виде `Name: value`.
+    pub fn add_header(&mut self, raw: &str) -> AnyResult<()> {
+        let (name, value) = raw
+            .split_once(':')
+            .ok_or_else(|| anyhow!("заголовок должен быть в виде `Name: value`: {raw}"))?;
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(anyhow!("пустое имя заголовка: {raw}"));
+        }
+        self.headers.push((name.to_string(), value.trim().to_string()));
+        Ok(())
+    }
+
+    /// Загрузить cookies из файла в формате Netscape.
+    pub fn load_cookie_file(&mut self, path: &Path) -> AnyResult<()> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("чтение файла cookies {}", path.display()))?;
+
+        for (n, line) in text.lines().enumerate() {
+            let (line, http_only) = match line.strip_prefix("#HttpOnly_") {
+                Some(rest) => (rest, true),
+                None => (line, false),
+            };
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let f: Vec<&str> = line.split('\t').collect();
+            if f.len() < 7 {
+                return Err(anyhow!("{}:{}: ожидается 7 полей через TAB", path.display(), n + 1));
+            }
+
+            self.cookies.push(Cookie {
+                domain: f[0].to_string(),
+                include_subdomains: f[1].eq_ignore_ascii_case("TRUE"),
+                path: f[2].to_string(),
+                secure: f[3].eq_ignore_ascii_case("TRUE"),
+                http_only,
+                expires: f[4].parse().unwrap_or(0),
+                name: f[5].to_string(),
+                value: f[6].to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Cookies для `url`, кроме истёкших.
+    pub fn cookies_for(&self, url: &Url) -> impl Iterator<Item = &Cookie> {
+        let url = url.clone();
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0);
+        self.cookies
+            .iter()
+            .filter(move |c| !c.is_expired(now) && c.matches(&url))
+    }
+}
+
+/// Учётные данные вместе со скоупом: отдаются только хостам из скоупа
+/// и никогда — web.archive.org.
+#[derive(Debug, Clone)]
+pub struct AuthPolicy {
+    creds: Credentials,
+    scope: Scope,
+}
+
+impl AuthPolicy {
+    pub fn new(creds: Credentials, scope: Scope) -> Self {
+        Self { creds, scope }
+    }
+
+    pub fn credentials(&self) -> &Credentials {
+        &self.creds
+    }
+
+    /// URL, если ему можно отдавать учётные данные.
+    pub fn applies_to(&self, url: &str) -> Option<Url> {
+        let parsed = Url::parse(url).ok()?;
+        if is_archive_url(&parsed) || !self.scope.allows(url) {
+            return None;
+        }
+        Some(parsed)
+    }
+
+    /// Заголовки для запроса к `url`: пользовательские, `Authorization: Bearer`
+    /// и, если `with_cookies`, `Cookie`. Пусто для URL вне скоупа.
+    pub fn headers_for(&self, url: &str, with_cookies: bool) -> Vec<(String, String)> {
+        let parsed = match self.applies_to(url) {
+            Some(u) => u,
+            None => return Vec::new(),
+        };
+
+        let mut out = self.creds.headers.clone();
+        let has = |out: &[(String, String)], name: &str| {
+            out.iter().any(|(n, _)| n.eq_ignore_ascii_case(name))
+        };
+
+        if let Some(token) = &self.creds.bearer {
+            if !has(&out, "Authorization") {
+                out.push(("Authorization".to_string(), format!("Bearer {token}")));
+            }
+        }
+
+        if with_cookies && !has(&out, "Cookie") {
+            let jar: Vec<String> = self
+                .creds
+                .cookies_for(&parsed)
+                .map(|c| format!("{}={}", c.name, c.value))
+                .collect();
+            if !jar.is_empty() {
+                out.push(("Cookie".to_string(), jar.join("; ")));
+            }
+        }
+        out
+    }
+}
+
+pub(crate) fn is_archive_url(url: &Url) -> bool {
+    url.host_str()
+        .is_some_and(|h| h.eq_ignore_ascii_case(ARCHIVE_HOST))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expired_cookies_are_skipped() {
+        let path = std::env::temp_dir().join(format!("webhound-cookies-{}.txt", std::process::id()));
+        fs::write(
+            &path,
+            "# Netscape HTTP Cookie File\n\
+             ex.com\tFALSE\t/\tFALSE\t0\tsession\ta\n\
+             ex.com\tFALSE\t/\tFALSE\t1000000000\told\tb\n\
+             #HttpOnly_ex.com\tFALSE\t/\tFALSE\t4102444800\tfresh\tc\n",
+        )
+        .unwrap();
+        let mut creds = Credentials::default();
+        creds.load_cookie_file(&path).unwrap();
+        let _ = fs::remove_file(&path);
+
+        let url = Url::parse("https://ex.com/").unwrap();
+        let names: Vec<&str> = creds.cookies_for(&url).map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["session", "fresh"]);
+    }
+}
@@ -1,32 +1,92 @@
 use anyhow::{Result, anyhow};
 use headless_chrome::{Browser, LaunchOptionsBuilder};
-use std::sync::{Arc, Mutex};
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
 use portpicker::pick_unused_port;
+use tempfile::TempDir;
+
+/// Настройки запуска Chrome для скриншотов.
+#[derive(Debug, Clone, Default)]
+pub struct BrowserOptions {
+    /// `http://host:port` или `socks5://host:port`.
+    pub proxy: Option<String>,
+    /// Не проверять сертификаты вообще (`--browser-insecure`). Chrome не берёт
+    /// CA из `--ca-cert`, поэтому за перехватывающим прокси без этого не обойтись,
+    /// но скриншоты при этом снимаются и с поддельными сертификатами.
+    pub ignore_cert_errors: bool,
+}
+
+/// Запущенный Chrome и папка с его скриптом-обёрткой: живёт, пока жив браузер.
+struct Running {
+    browser: Arc<Browser>,
+    _launcher: Option<TempDir>,
+}
 
 pub struct BrowserManager {
-    inner: Mutex<Option<Arc<Browser>>>,
+    inner: Mutex<Option<Running>>,
+    options: Mutex<BrowserOptions>,
 }
 
 impl BrowserManager {
     pub const fn new() -> Self {
         Self {
             inner: Mutex::new(None),
+            options: Mutex::new(BrowserOptions {
+                proxy: None,
+                ignore_cert_errors: false,
+            }),
         }
     }
 
-    fn launch_browser() -> Result<Arc<Browser>> {
+    /// Задать настройки; уже запущенный браузер будет перезапущен при следующем `get`.
+    pub fn configure(&self, opts: BrowserOptions) -> Result<()> {
+        *self
+            .options
+            .lock()
+            .map_err(|e| anyhow!("mutex poisoned in BrowserManager::configure: {e}"))? = opts;
+        self.invalidate()
+    }
+
+    pub fn options(&self) -> Result<BrowserOptions> {
+        self.options
+            .lock()
+            .map(|o| o.clone())
+            .map_err(|e| anyhow!("mutex poisoned in BrowserManager::options: {e}"))
+    }
+
+    fn launch_browser(opts: &BrowserOptions) -> Result<Running> {
         let port = pick_unused_port().unwrap_or(0);
         let mut builder = LaunchOptionsBuilder::default();
         builder.headless(true);
         builder.port(Some(port));
 
+        let mut launcher = None;
+        if let Some(proxy) = &opts.proxy {
+            // переменные окружения Chrome слушает только без DE,
+            // поэтому прокси передаётся ещё и флагом через обёртку
+            let envs: HashMap<String, String> = ["http_proxy", "https_proxy", "all_proxy"]
+                .iter()
+                .map(|var| (var.to_string(), proxy.clone()))
+                .collect();
+            builder.process_envs(Some(envs));
+            let (dir, script) = proxied_launcher(proxy)?;
+            builder.path(Some(script));
+            launcher = Some(dir);
+        }
+
         let launch_opts = builder
             .build()
             .map_err(|e| anyhow!("building LaunchOptions: {e}"))?;
 
         let browser =
             Browser::new(launch_opts).map_err(|e| anyhow!("starting headless chrome: {e}"))?;
-        Ok(Arc::new(browser))
+        Ok(Running {
+            browser: Arc::new(browser),
+            _launcher: launcher,
+        })
     }
 
     pub fn get(&self) -> Result<Arc<Browser>> {
@@ -34,19 +94,20 @@ impl BrowserManager {
         match self.inner.lock() {
             Ok(guard) => {
                 if let Some(existing) = guard.as_ref() {
-                    return Ok(existing.clone());
+                    return Ok(existing.browser.clone());
                 }
             }
             Err(e) => return Err(anyhow!("mutex poisoned in BrowserManager::get(read): {e}")),
         }
 
-        let fresh = Self::launch_browser()?;
+        let fresh = Self::launch_browser(&self.options()?)?;
+        let browser = fresh.browser.clone();
         let mut guard = self
             .inner
             .lock()
             .map_err(|e| anyhow!("mutex poisoned in BrowserManager::get(write): {e}"))?;
-        *guard = Some(fresh.clone());
-        Ok(fresh)
+        *guard = Some(fresh);
+        Ok(browser)
     }
 
     pub fn invalidate(&self) -> Result<()> {
@@ -59,4 +120,97 @@ impl BrowserManager {
     }
 }
 
+/// headless_chrome 0.9 не умеет передавать свои аргументы Chrome, поэтому
+/// `--proxy-server` добавляет скрипт-обёртка, запускаемый вместо Chrome. Скрипт
+/// лежит в личной папке (0700) и создаётся с `create_new`, чтобы его нельзя
+/// было подменить; папка удаляется вместе с `TempDir`.
+#[cfg(unix)]
+fn proxied_launcher(proxy: &str) -> Result<(TempDir, PathBuf)> {
+    use std::{
+        fs::Permissions,
+        io::Write,
+        os::unix::fs::{OpenOptionsExt, PermissionsExt},
+    };
+
+    let chrome = headless_chrome::browser::default_executable()
+        .map_err(|e| anyhow!("поиск Chrome: {e}"))?;
+    let dir = tempfile::Builder::new()
+        .prefix("webhound-chrome-")
+        .permissions(Permissions::from_mode(0o700))
+        .tempdir()
+        .map_err(|e| anyhow!("папка для обёртки Chrome: {e}"))?;
+    let script = dir.path().join("chrome.sh");
+    std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o700)
+        .open(&script)
+        .and_then(|mut f| f.write_all(launcher_script(&chrome, proxy).as_bytes()))
+        .map_err(|e| anyhow!("запись {}: {e}", script.display()))?;
+    Ok((dir, script))
+}
+
+#[cfg(not(unix))]
+fn proxied_launcher(_proxy: &str) -> Result<(TempDir, PathBuf)> {
+    Err(anyhow!(
+        "--proxy для Chrome поддерживается только на Unix: скриншоты пошли бы мимо прокси"
+    ))
+}
+
+#[cfg(unix)]
+fn launcher_script(chrome: &std::path::Path, proxy: &str) -> String {
+    format!(
+        "#!/bin/sh\nexec {} {} \"$@\"\n",
+        sh_quote(&chrome.to_string_lossy()),
+        sh_quote(&format!("--proxy-server={proxy}"))
+    )
+}
+
+#[cfg(unix)]
+fn sh_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+impl Default for BrowserManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 pub static BROWSER_MANAGER: BrowserManager = BrowserManager::new();
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::{os::unix::fs::PermissionsExt, path::Path, process::Command};
+
+    #[test]
+    fn launcher_lives_in_private_dir_and_is_removed_with_it() {
+        // вместо Chrome — echo: обёртка должна передать ему флаг и свои аргументы
+        std::env::set_var("CHROME", "/bin/echo");
+        let (dir, script) = proxied_launcher("http://127.0.0.1:8080").unwrap();
+        let dir_path = dir.path().to_path_buf();
+        assert!(script.starts_with(&dir_path));
+        assert_eq!(std::fs::metadata(&dir_path).unwrap().permissions().mode() & 0o777, 0o700);
+        assert_eq!(std::fs::metadata(&script).unwrap().permissions().mode() & 0o777, 0o700);
+
+        let out = Command::new(&script).arg("--headless").output().unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(&out.stdout),
+            "--proxy-server=http://127.0.0.1:8080 --headless\n"
+        );
+
+        drop(dir);
+        assert!(!dir_path.exists());
+        assert!(!script.exists());
+    }
+
+    #[test]
+    fn launcher_passes_proxy_flag_and_chrome_args() {
+        let script = launcher_script(Path::new("/opt/it's chrome/chrome"), "http://127.0.0.1:8080");
+        assert_eq!(
+            script,
+            "#!/bin/sh\nexec '/opt/it'\\''s chrome/chrome' '--proxy-server=http://127.0.0.1:8080' \"$@\"\n"
+        );
+    }
+}
@@ -0,0 +1,54 @@
+//! CDP-методы, которых нет в `headless_chrome` 0.9.
+
+use headless_chrome::protocol::Method;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SetIgnoreCertificateErrors {
+    pub ignore: bool,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct EmptyReturnObject {}
+
+impl Method for SetIgnoreCertificateErrors {
+    const NAME: &'static str = "Security.setIgnoreCertificateErrors";
+    type ReturnObject = EmptyReturnObject;
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CookieParam {
+    pub name: String,
+    pub value: String,
+    /// Cookie без `domain`, привязанная только к хосту этого URL.
+    pub url: String,
+    pub path: String,
+    pub secure: bool,
+    pub http_only: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires: Option<f64>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SetCookies {
+    pub cookies: Vec<CookieParam>,
+}
+
+impl Method for SetCookies {
+    const NAME: &'static str = "Network.setCookies";
+    type ReturnObject = EmptyReturnObject;
+}
+
+/// Заголовки для всех запросов вкладки.
+#[derive(Serialize, Debug)]
+pub struct SetExtraHttpHeaders {
+    pub headers: HashMap<String, String>,
+}
+
+impl Method for SetExtraHttpHeaders {
+    const NAME: &'static str = "Network.setExtraHTTPHeaders";
+    type ReturnObject = EmptyReturnObject;
+}
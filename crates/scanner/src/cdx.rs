@@ -0,0 +1,353 @@
+use crate::fetch::Fetcher;
+
+use anyhow::{Context, Result as AnyResult};
+use reqwest::{Response, Url};
+
+/// Поля, которые запрашиваются у CDX, в порядке колонок ответа.
+pub const CDX_FIELDS: &str = "original,timestamp,mimetype,statuscode,digest,length";
+
+/// Имя файла в папке скана, куда пишутся записи CDX (`CdxRecord::to_tsv`).
+pub const CDX_FILE: &str = "cdx.tsv";
+
+/// Сколько записей просить у CDX за одну страницу.
+pub const DEFAULT_PAGE_SIZE: usize = 5000;
+
+/// Одна запись индекса Wayback.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CdxRecord {
+    pub original: String,
+    /// `yyyyMMddhhmmss`.
+    pub timestamp: String,
+    pub mimetype: String,
+    pub statuscode: String,
+    pub digest: String,
+    pub length: Option<u64>,
+}
+
+impl CdxRecord {
+    /// Строка текстового ответа CDX с полями `CDX_FIELDS` через пробел.
+    pub fn parse_line(line: &str) -> Option<Self> {
+        Self::from_fields(line.split_whitespace())
+    }
+
+    /// Строка `cdx.tsv`.
+    pub fn from_tsv(line: &str) -> Option<Self> {
+        Self::from_fields(line.split('\t'))
+    }
+
+    fn from_fields<'a>(mut f: impl Iterator<Item = &'a str>) -> Option<Self> {
+        let original = f.next()?.to_string();
+        let timestamp = f.next()?.to_string();
+        if original.is_empty() || !timestamp.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        Some(Self {
+            original,
+            timestamp,
+            mimetype: f.next().unwrap_or("-").to_string(),
+            statuscode: f.next().unwrap_or("-").to_string(),
+            digest: f.next().unwrap_or("-").to_string(),
+            length: f.next().and_then(|s| s.parse().ok()),
+        })
+    }
+
+    pub fn to_tsv(&self) -> String {
+        let length = self.length.map(|n| n.to_string()).unwrap_or_else(|| "-".into());
+        format!(
+            "{}\t{}\t{}\t{}\t{}\t{}",
+            self.original, self.timestamp, self.mimetype, self.statuscode, self.digest, length
+        )
+    }
+
+    /// Снимок без тулбара Wayback.
+    pub fn archived_url(&self, base_url: &str) -> String {
+        format!(
+            "{}/web/{}id_/{}",
+            base_url.trim_end_matches('/'),
+            self.timestamp,
+            self.original
+        )
+    }
+}
+
+/// Фильтры, которые CDX применяет на своей стороне.
+///
+/// `statuses` и `mimetypes` — регулярные выражения CDX; значение с `!` в начале
+/// исключает совпавшие записи, остальные объединяются через «или».
+#[derive(Debug, Clone, Default)]
+pub struct CdxFilter {
+    /// Не раньше этой даты (`yyyy`, `yyyyMM`, … `yyyyMMddhhmmss`).
+    pub from: Option<String>,
+    /// Не позже этой даты.
+    pub to: Option<String>,
+    pub statuses: Vec<String>,
+    pub mimetypes: Vec<String>,
+}
+
+impl CdxFilter {
+    pub fn is_empty(&self) -> bool {
+        self.from.is_none() && self.to.is_none() && self.statuses.is_empty() && self.mimetypes.is_empty()
+    }
+
+    pub(crate) fn apply(&self, url: &mut Url) {
+        let mut q = url.query_pairs_mut();
+        if let Some(from) = &self.from {
+            q.append_pair("from", from);
+        }
+        if let Some(to) = &self.to {
+            q.append_pair("to", to);
+        }
+        for (field, values) in [("statuscode", &self.statuses), ("mimetype", &self.mimetypes)] {
+            let mut allow = Vec::new();
+            for v in values.iter().map(|v| v.trim()).filter(|v| !v.is_empty()) {
+                match v.strip_prefix('!') {
+                    Some(deny) => {
+                        q.append_pair("filter", &format!("!{field}:{deny}"));
+                    }
+                    None => allow.push(v),
+                }
+            }
+            // несколько положительных filter= CDX объединяет через «и»
+            if !allow.is_empty() {
+                q.append_pair("filter", &format!("{field}:({})", allow.join("|")));
+            }
+        }
+    }
+}
+
+/// Все URL домена и его поддоменов (по записи на `urlkey`), постранично через
+/// `showResumeKey`. Записи отдаются в `on_record` по мере чтения ответа.
+pub async fn list_domain(
+    fetcher: &Fetcher,
+    base_url: &str,
+    domain: &str,
+    filter: &CdxFilter,
+    page_size: usize,
+    mut on_record: impl FnMut(CdxRecord) -> AnyResult<()>,
+) -> AnyResult<usize> {
+    let mut total = 0;
+    let mut resume: Option<String> = None;
+
+    loop {
+        let mut q = cdx_endpoint(base_url)?;
+        q.query_pairs_mut()
+            .append_pair("url", &format!("{domain}/*"))
+            .append_pair("matchType", "domain")
+            .append_pair("collapse", "urlkey")
+            .append_pair("fl", CDX_FIELDS)
+            .append_pair("limit", &page_size.max(1).to_string())
+            .append_pair("showResumeKey", "true");
+        if let Some(key) = &resume {
+            q.query_pairs_mut().append_pair("resumeKey", key);
+        }
+        filter.apply(&mut q);
+
+        let resp = fetcher.send(fetcher.get(q.as_str())).await?;
+        anyhow::ensure!(resp.status().is_success(), "CDX failed: {} -> {}", q, resp.status());
+
+        // после пустой строки CDX пишет ключ следующей страницы
+        let mut after_blank = false;
+        let mut key = None;
+        for_each_line(resp, |line| {
+            if line.trim().is_empty() {
+                after_blank = true;
+            } else if after_blank {
+                key = Some(decode_key(line.trim()));
+            } else if let Some(rec) = CdxRecord::parse_line(line) {
+                total += 1;
+                on_record(rec)?;
+            }
+            Ok(())
+        })
+        .await?;
+
+        match key {
+            Some(k) if resume.as_deref() != Some(k.as_str()) => resume = Some(k),
+            _ => break,
+        }
+    }
+    Ok(total)
+}
+
+fn cdx_endpoint(base_url: &str) -> AnyResult<Url> {
+    let full = format!("{}/cdx/search/cdx", base_url.trim_end_matches('/'));
+    Url::parse(&full).with_context(|| format!("неверный адрес CDX {full}"))
+}
+
+/// Ключ приходит уже в URL-кодировке; раскодируем, чтобы не закодировать дважды.
+fn decode_key(raw: &str) -> String {
+    url::form_urlencoded::parse(format!("k={raw}").as_bytes())
+        .next()
+        .map(|(_, v)| v.into_owned())
+        .unwrap_or_else(|| raw.to_string())
+}
+
+/// Читать тело ответа построчно, не держа его целиком в памяти.
+pub(crate) async fn for_each_line(
+    mut resp: Response,
+    mut f: impl FnMut(&str) -> AnyResult<()>,
+) -> AnyResult<()> {
+    let mut buf: Vec<u8> = Vec::new();
+    while let Some(chunk) = resp.chunk().await? {
+        buf.extend_from_slice(&chunk);
+        while let Some(pos) = buf.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = buf.drain(..=pos).collect();
+            f(String::from_utf8_lossy(&line).trim_end_matches(['\r', '\n']))?;
+        }
+    }
+    if !buf.is_empty() {
+        f(String::from_utf8_lossy(&buf).trim_end_matches('\r'))?;
+    }
+    Ok(())
+}
+
+/// Какие снимки URL из Wayback анализировать.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Snapshots {
+    /// Только последний снимок со статусом 200, если живой URL недоступен.
+    #[default]
+    Latest,
+    /// До N снимков, равномерно по времени.
+    Spread(usize),
+    /// Каждый снимок с новым digest, не больше N (`0` — все).
+    Digests(usize),
+}
+
+impl Snapshots {
+    /// Отобрать снимки из записей, упорядоченных по времени.
+    pub fn select(&self, records: Vec<CdxRecord>) -> Vec<CdxRecord> {
+        match *self {
+            Snapshots::Latest => records.into_iter().last().into_iter().collect(),
+            Snapshots::Spread(n) => {
+                let m = records.len();
+                if n == 0 || m <= n {
+                    return records;
+                }
+                if n == 1 {
+                    return records.into_iter().last().into_iter().collect();
+                }
+                let picks: Vec<usize> = (0..n).map(|i| i * (m - 1) / (n - 1)).collect();
+                records
+                    .into_iter()
+                    .enumerate()
+                    .filter(|(i, _)| picks.contains(i))
+                    .map(|(_, r)| r)
+                    .collect()
+            }
+            Snapshots::Digests(max) => {
+                let mut seen = std::collections::HashSet::new();
+                let mut out: Vec<CdxRecord> = records
+                    .into_iter()
+                    .filter(|r| seen.insert(r.digest.clone()))
+                    .collect();
+                if max > 0 && out.len() > max {
+                    out = Snapshots::Spread(max).select(out);
+                }
+                out
+            }
+        }
+    }
+}
+
+/// Все снимки одного URL со статусом 200, без картинок и медиа; соседние
+/// снимки с одинаковым содержимым CDX схлопывает сам.
+pub async fn list_snapshots(
+    fetcher: &Fetcher,
+    base_url: &str,
+    url: &str,
+) -> AnyResult<Vec<CdxRecord>> {
+    let mut q = cdx_endpoint(base_url)?;
+    q.query_pairs_mut()
+        .append_pair("url", url)
+        .append_pair("fl", CDX_FIELDS)
+        .append_pair("filter", "statuscode:200")
+        .append_pair("filter", "!mimetype:(image|video|audio|font)/.*")
+        .append_pair("collapse", "digest");
+
+    let resp = fetcher.send(fetcher.get(q.as_str())).await?;
+    anyhow::ensure!(resp.status().is_success(), "CDX failed: {} -> {}", q, resp.status());
+
+    let mut out = Vec::new();
+    for_each_line(resp, |line| {
+        out.extend(CdxRecord::parse_line(line));
+        Ok(())
+    })
+    .await?;
+    out.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server::{block_on, fetcher, serve, Reply};
+
+    #[test]
+    fn list_domain_resumes_until_no_key() {
+        block_on(async {
+            let (base, log) = serve(|req| {
+                assert_eq!(req.path(), "/cdx/search/cdx");
+                match req.query("resumeKey").as_deref() {
+                    None => Reply::new(
+                        200,
+                        "https://ex.com/a.js 20200101000000 application/javascript 200 AAA 10\n\
+                         https://ex.com/b.js 20200102000000 application/javascript 200 BBB 20\n\
+                         \n\
+                         com%2Cex%29%2Fc.js+20200103000000\n",
+                    ),
+                    Some("com,ex)/c.js 20200103000000") => Reply::new(
+                        200,
+                        "https://ex.com/c.js 20200103000000 application/javascript 200 CCC 30\n",
+                    ),
+                    Some(other) => panic!("неожиданный resumeKey {other}"),
+                }
+            })
+            .await;
+
+            let mut seen = Vec::new();
+            let filter = CdxFilter {
+                statuses: vec!["200".to_string()],
+                ..CdxFilter::default()
+            };
+            let n = list_domain(&fetcher(), &base, "ex.com", &filter, 2, |rec| {
+                seen.push(rec.original);
+                Ok(())
+            })
+            .await
+            .unwrap();
+
+            assert_eq!(n, 3);
+            assert_eq!(seen, ["https://ex.com/a.js", "https://ex.com/b.js", "https://ex.com/c.js"]);
+            let log = log.lock().unwrap();
+            assert_eq!(log.len(), 2);
+            assert_eq!(log[0].query("url").as_deref(), Some("ex.com/*"));
+            assert_eq!(log[0].query("showResumeKey").as_deref(), Some("true"));
+            assert_eq!(log[0].query("limit").as_deref(), Some("2"));
+            assert_eq!(log[0].query("filter").as_deref(), Some("statuscode:(200)"));
+        });
+    }
+
+    #[test]
+    fn list_domain_stops_on_repeated_key() {
+        block_on(async {
+            let (base, log) = serve(|_| {
+                Reply::new(200, "https://ex.com/ 20200101000000 text/html 200 AAA 1\n\nsame-key\n")
+            })
+            .await;
+            let n = list_domain(&fetcher(), &base, "ex.com", &CdxFilter::default(), 1, |_| Ok(()))
+                .await
+                .unwrap();
+            assert_eq!(n, 2);
+            assert_eq!(log.lock().unwrap().len(), 2);
+        });
+    }
+
+    #[test]
+    fn list_domain_fails_on_error_status() {
+        block_on(async {
+            let (base, _) = serve(|_| Reply::new(500, "")).await;
+            let res = list_domain(&fetcher(), &base, "ex.com", &CdxFilter::default(), 1, |_| Ok(())).await;
+            assert!(res.is_err());
+        });
+    }
+}
@@ -0,0 +1,508 @@
+use crate::auth::AuthPolicy;
+use crate::cdx::{self, CdxRecord, Snapshots};
+use crate::scan_config::{
+    DEFAULT_READ_TIMEOUT, DEFAULT_RETRIES, DEFAULT_RETRY_BACKOFF, DEFAULT_USER_AGENT, ScanConfig,
+};
+
+use anyhow::{Result as AnyResult, anyhow};
+use reqwest::{
+    Client, RequestBuilder, Response, StatusCode, Url,
+    header::{RETRY_AFTER, USER_AGENT},
+};
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::Arc,
+    time::{Instant, SystemTime},
+};
+use tokio::{
+    sync::{Mutex, Semaphore},
+    time::{Duration, sleep},
+};
+
+pub(crate) const WAYBACK_BASE: &str = "https://web.archive.org";
+
+/// Сколько раз повторять запрос, на который хост ответил 429/503.
+const THROTTLE_RETRIES: usize = 3;
+
+/// Лимиты запросов: токен-бакет на каждый хост и общий потолок одновременных запросов.
+#[derive(Debug, Clone)]
+pub struct RateLimit {
+    /// Запросов в секунду на хост; `0` — без ограничения.
+    pub per_host_rps: f64,
+    /// Сколько запросов к хосту можно сделать подряд без ожидания.
+    pub burst: u32,
+    /// Одновременных запросов на весь скан.
+    pub max_in_flight: usize,
+    /// Потолок паузы, если хост отвечает 429/503 без `Retry-After`.
+    pub max_backoff: Duration,
+}
+
+impl Default for RateLimit {
+    fn default() -> Self {
+        Self {
+            per_host_rps: 5.0,
+            burst: 5,
+            max_in_flight: 16,
+            max_backoff: Duration::from_secs(120),
+        }
+    }
+}
+
+impl RateLimit {
+    pub fn unlimited() -> Self {
+        Self {
+            per_host_rps: 0.0,
+            burst: 1,
+            max_in_flight: Semaphore::MAX_PERMITS,
+            max_backoff: Duration::from_secs(120),
+        }
+    }
+}
+
+/// Результат `Fetcher::fetch`.
+pub struct Fetched {
+    pub body: Vec<u8>,
+    /// Сам URL или адрес снимка в Wayback.
+    pub final_url: String,
+    pub from_wayback: bool,
+    /// Код ответа живого сайта; `None` — не ответил.
+    pub live_status: Option<u16>,
+}
+
+/// Ошибка `Fetcher::fetch`: ни живой страницы, ни снимка. Код живого ответа
+/// (например, 404) сохраняется для `url_status.csv`.
+#[derive(Debug)]
+pub struct FetchFailed {
+    /// Код ответа живого сайта; `None` — не ответил.
+    pub live_status: Option<u16>,
+    pub source: anyhow::Error,
+}
+
+impl fmt::Display for FetchFailed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.live_status {
+            Some(code) => write!(f, "живой ответ {code}, {:#}", self.source),
+            None => write!(f, "{:#}", self.source),
+        }
+    }
+}
+
+impl std::error::Error for FetchFailed {}
+
+struct HostState {
+    tokens: f64,
+    refilled: Instant,
+    blocked_until: Option<Instant>,
+    backoff: Duration,
+}
+
+impl HostState {
+    fn new(burst: f64, now: Instant) -> Self {
+        Self {
+            tokens: burst,
+            refilled: now,
+            blocked_until: None,
+            backoff: Duration::ZERO,
+        }
+    }
+
+    /// Взять токен в момент `now`. `None` — можно отправлять, `Some` — сколько ждать.
+    fn take(&mut self, now: Instant, rps: f64, burst: f64) -> Option<Duration> {
+        match self.blocked_until {
+            Some(until) if until > now => return Some(until - now),
+            _ => self.blocked_until = None,
+        }
+        if rps <= 0.0 {
+            return None;
+        }
+
+        let elapsed = now.saturating_duration_since(self.refilled).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * rps).min(burst);
+        self.refilled = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            return None;
+        }
+        Some(Duration::from_secs_f64((1.0 - self.tokens) / rps))
+    }
+}
+
+/// Общий слой загрузки: все запросы сканера идут через него.
+pub struct Fetcher {
+    client: Client,
+    limits: RateLimit,
+    hosts: Mutex<HashMap<String, HostState>>,
+    in_flight: Semaphore,
+    user_agent: String,
+    read_timeout: Duration,
+    retries: usize,
+    retry_backoff: Duration,
+    auth: Option<Arc<AuthPolicy>>,
+    snapshots: Snapshots,
+}
+
+impl Fetcher {
+    /// Таймауты, повторы и User-Agent — как в `ScanConfig` по умолчанию.
+    pub fn new(client: Client, limits: RateLimit) -> Self {
+        let in_flight = Semaphore::new(limits.max_in_flight.clamp(1, Semaphore::MAX_PERMITS));
+        Self {
+            client,
+            limits,
+            hosts: Mutex::new(HashMap::new()),
+            in_flight,
+            user_agent: DEFAULT_USER_AGENT.to_string(),
+            read_timeout: DEFAULT_READ_TIMEOUT,
+            retries: DEFAULT_RETRIES,
+            retry_backoff: DEFAULT_RETRY_BACKOFF,
+            auth: None,
+            snapshots: Snapshots::Latest,
+        }
+    }
+
+    pub fn from_config(cfg: &ScanConfig) -> AnyResult<Self> {
+        let mut fetcher = Self::new(cfg.build_client()?, cfg.rate_limit.clone());
+        fetcher.user_agent = cfg.user_agent.clone();
+        fetcher.read_timeout = cfg.read_timeout;
+        fetcher.retries = cfg.retries;
+        fetcher.retry_backoff = cfg.retry_backoff;
+        fetcher.snapshots = cfg.snapshots;
+        if !cfg.credentials.is_empty() {
+            fetcher.auth = Some(Arc::new(AuthPolicy::new(
+                cfg.credentials.clone(),
+                cfg.scope.clone(),
+            )));
+        }
+        Ok(fetcher)
+    }
+
+    pub fn auth(&self) -> Option<Arc<AuthPolicy>> {
+        self.auth.clone()
+    }
+
+    pub fn snapshots(&self) -> Snapshots {
+        self.snapshots
+    }
+
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    /// Лимит на ответ одного запроса (`--timeout`).
+    pub fn read_timeout(&self) -> Duration {
+        self.read_timeout
+    }
+
+    /// GET с User-Agent сканера и, для хостов из скоупа, учётными данными.
+    pub fn get(&self, url: &str) -> RequestBuilder {
+        let mut rb = self.client.get(url).header(USER_AGENT, self.user_agent.as_str());
+        if let Some(auth) = &self.auth {
+            for (name, value) in auth.headers_for(url, true) {
+                rb = rb.header(name, value);
+            }
+        }
+        rb
+    }
+
+    /// Отправить запрос с учётом лимитов хоста. На 429/503 хост ставится на паузу
+    /// (`Retry-After` или экспоненциальный backoff) и запрос повторяется
+    /// `THROTTLE_RETRIES` раз, потом — ошибка; таймауты и ошибки соединения
+    /// повторяются `retries` раз.
+    pub async fn send(&self, rb: RequestBuilder) -> AnyResult<Response> {
+        let req = rb.build()?;
+        let host = req.url().host_str().unwrap_or("").to_ascii_lowercase();
+
+        let mut attempt = 0;
+        let mut failures = 0;
+        loop {
+            self.wait_turn(&host).await;
+
+            let next = req
+                .try_clone()
+                .ok_or_else(|| anyhow!("запрос к {} нельзя повторить", req.url()))?;
+            let sent = {
+                let _permit = self.in_flight.acquire().await?;
+                self.client.execute(next).await
+            };
+
+            let resp = match sent {
+                Ok(r) => r,
+                Err(e) if (e.is_timeout() || e.is_connect()) && failures < self.retries => {
+                    sleep(self.retry_backoff * 2u32.saturating_pow(failures as u32)).await;
+                    failures += 1;
+                    continue;
+                }
+                Err(e) => return Err(e.into()),
+            };
+
+            let throttled = matches!(
+                resp.status(),
+                StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE
+            );
+            if !throttled {
+                self.relax(&host).await;
+                return Ok(resp);
+            }
+
+            let pause = self.throttle(&host, retry_after(&resp)).await;
+            if attempt >= THROTTLE_RETRIES {
+                return Err(anyhow!(
+                    "{host} ответил {} и после {THROTTLE_RETRIES} повторов",
+                    resp.status()
+                ));
+            }
+            attempt += 1;
+            eprintln!(
+                "[~] {host} ответил {}, пауза {}s (попытка {attempt}/{THROTTLE_RETRIES})",
+                resp.status(),
+                pause.as_secs()
+            );
+        }
+    }
+
+    /// Живой URL, а если он недоступен — последний снимок из Wayback со статусом 200.
+    /// Возвращает `(тело, фактический URL, взято_из_wayback)`.
+    pub async fn fetch_live_or_wayback(
+        &self,
+        original_url: &str,
+    ) -> AnyResult<(Vec<u8>, String, bool)> {
+        let f = self.fetch(original_url).await?;
+        Ok((f.body, f.final_url, f.from_wayback))
+    }
+
+    /// Как `fetch_live_or_wayback`, но вместе с кодом ответа живого сайта.
+    pub async fn fetch(&self, original_url: &str) -> AnyResult<Fetched> {
+        let live = self.get(original_url).timeout(self.read_timeout);
+        let mut live_status = None;
+        if let Ok(ok) = self.send(live).await {
+            live_status = Some(ok.status().as_u16());
+            if ok.status().is_success() {
+                let data = ok.bytes().await?;
+                return Ok(Fetched {
+                    body: data.to_vec(),
+                    final_url: original_url.to_string(),
+                    from_wayback: false,
+                    live_status,
+                });
+            }
+        }
+
+        let (body, final_url) = self
+            .latest_snapshot(original_url)
+            .await
+            .map_err(|source| FetchFailed { live_status, source })?;
+        Ok(Fetched {
+            body,
+            final_url,
+            from_wayback: true,
+            live_status,
+        })
+    }
+
+    /// Последний снимок `url` с кодом 200: тело и адрес снимка.
+    async fn latest_snapshot(&self, original_url: &str) -> AnyResult<(Vec<u8>, String)> {
+        let cdx = latest_snapshot_query(WAYBACK_BASE, original_url)?;
+        let cdx_resp = self.send(self.get(cdx.as_str())).await?;
+        if cdx_resp.status() != StatusCode::OK {
+            return Err(anyhow!("Wayback CDX status {} for {}", cdx_resp.status(), original_url));
+        }
+
+        let val: serde_json::Value = serde_json::from_slice(&cdx_resp.bytes().await?)?;
+        let ts = val.as_array()
+            .and_then(|arr| arr.get(1))
+            .and_then(|row| row.get(0))
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow!("Wayback: нет timestamp для {}", original_url))?;
+
+        let archived = format!("{WAYBACK_BASE}/web/{}id_/{}", ts, original_url);
+        let resp = self
+            .send(self.get(&archived).timeout(self.read_timeout))
+            .await?
+            .error_for_status()?;
+        Ok((resp.bytes().await?.to_vec(), archived))
+    }
+
+    /// Снимки `url` из Wayback, отобранные по режиму `self.snapshots()`.
+    pub async fn list_snapshots(&self, url: &str) -> AnyResult<Vec<CdxRecord>> {
+        let all = cdx::list_snapshots(self, WAYBACK_BASE, url).await?;
+        Ok(self.snapshots.select(all))
+    }
+
+    /// Тело снимка как есть, без тулбара Wayback.
+    pub async fn fetch_snapshot(&self, rec: &CdxRecord) -> AnyResult<Vec<u8>> {
+        let resp = self
+            .send(self.get(&rec.archived_url(WAYBACK_BASE)).timeout(self.read_timeout))
+            .await?
+            .error_for_status()?;
+        Ok(resp.bytes().await?.to_vec())
+    }
+
+    async fn wait_turn(&self, host: &str) {
+        loop {
+            let wait = {
+                let mut hosts = self.hosts.lock().await;
+                let burst = self.limits.burst.max(1) as f64;
+                let now = Instant::now();
+                let st = hosts
+                    .entry(host.to_string())
+                    .or_insert_with(|| HostState::new(burst, now));
+                match st.take(now, self.limits.per_host_rps, burst) {
+                    Some(wait) => wait,
+                    None => return,
+                }
+            };
+            sleep(wait).await;
+        }
+    }
+
+    /// Поставить хост на паузу; без `Retry-After` пауза удваивается с каждым отказом.
+    async fn throttle(&self, host: &str, retry_after: Option<Duration>) -> Duration {
+        let mut hosts = self.hosts.lock().await;
+        let st = match hosts.get_mut(host) {
+            Some(st) => st,
+            None => return Duration::ZERO,
+        };
+
+        st.backoff = (st.backoff * 2)
+            .max(Duration::from_secs(1))
+            .min(self.limits.max_backoff);
+        let pause = retry_after.unwrap_or(st.backoff).min(self.limits.max_backoff);
+        st.blocked_until = Some(Instant::now() + pause);
+        pause
+    }
+
+    async fn relax(&self, host: &str) {
+        if let Some(st) = self.hosts.lock().await.get_mut(host) {
+            st.backoff = Duration::ZERO;
+        }
+    }
+}
+
+/// Запрос CDX за последним снимком `url` с кодом 200. `url` кодируется как
+/// значение параметра: его собственные `&`, `?` и `#` не ломают запрос.
+fn latest_snapshot_query(base: &str, url: &str) -> AnyResult<Url> {
+    let mut cdx = Url::parse(&format!("{}/cdx/search/cdx", base.trim_end_matches('/')))?;
+    cdx.query_pairs_mut()
+        .append_pair("url", url)
+        .append_pair("output", "json")
+        .append_pair("fl", "timestamp,original")
+        .append_pair("filter", "statuscode:200")
+        .append_pair("limit", "1")
+        .append_pair("sort", "descending");
+    Ok(cdx)
+}
+
+fn retry_after(resp: &Response) -> Option<Duration> {
+    let value = resp.headers().get(RETRY_AFTER)?.to_str().ok()?;
+    parse_retry_after(value, SystemTime::now())
+}
+
+/// `Retry-After`: число секунд или HTTP-дата (RFC 9110, 10.2.3). Дата в прошлом —
+/// повторять сразу; нераспознанное значение — `None`, и пауза берётся из backoff.
+fn parse_retry_after(value: &str, now: SystemTime) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let at = httpdate::parse_http_date(value).ok()?;
+    Some(at.duration_since(now).unwrap_or(Duration::ZERO))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server::{block_on, fetcher, serve, Reply};
+
+    #[test]
+    fn bucket_spends_burst_then_refills_at_rate() {
+        let start = Instant::now();
+        let mut st = HostState::new(3.0, start);
+
+        for _ in 0..3 {
+            assert_eq!(st.take(start, 2.0, 3.0), None);
+        }
+        // бакет пуст: при 2 rps следующий токен через полсекунды
+        assert_eq!(st.take(start, 2.0, 3.0), Some(Duration::from_millis(500)));
+        assert_eq!(st.take(start + Duration::from_millis(500), 2.0, 3.0), None);
+
+        // долгий простой не копит больше `burst`
+        let later = start + Duration::from_secs(60);
+        for _ in 0..3 {
+            assert_eq!(st.take(later, 2.0, 3.0), None);
+        }
+        assert!(st.take(later, 2.0, 3.0).is_some());
+    }
+
+    #[test]
+    fn blocked_host_waits_even_without_rate_limit() {
+        let now = Instant::now();
+        let mut st = HostState::new(1.0, now);
+        st.blocked_until = Some(now + Duration::from_secs(2));
+
+        assert_eq!(st.take(now, 0.0, 1.0), Some(Duration::from_secs(2)));
+        assert_eq!(st.take(now + Duration::from_secs(2), 0.0, 1.0), None);
+        assert_eq!(st.blocked_until, None);
+    }
+
+    #[test]
+    fn retry_after_seconds_and_http_date() {
+        let now = httpdate::parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT").unwrap();
+
+        assert_eq!(parse_retry_after(" 120 ", now), Some(Duration::from_secs(120)));
+        assert_eq!(
+            parse_retry_after("Sun, 06 Nov 1994 08:50:07 GMT", now),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            parse_retry_after("Sun, 06 Nov 1994 08:00:00 GMT", now),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after("-1", now), None);
+        assert_eq!(parse_retry_after("soon", now), None);
+    }
+
+    #[test]
+    fn latest_snapshot_query_keeps_url_whole() {
+        let url = "https://ex.com/p?a=1&limit=5#frag";
+        let q = latest_snapshot_query("https://web.archive.org/", url).unwrap();
+        assert_eq!(q.path(), "/cdx/search/cdx");
+
+        let pairs: Vec<(String, String)> = q.query_pairs().into_owned().collect();
+        let get = |k: &str| pairs.iter().filter(|(n, _)| n == k).map(|(_, v)| v.as_str()).collect::<Vec<_>>();
+        assert_eq!(get("url"), [url]);
+        assert_eq!(get("limit"), ["1"]);
+        assert_eq!(get("a"), Vec::<&str>::new());
+        assert_eq!(q.fragment(), None);
+    }
+
+    #[test]
+    fn throttled_host_gives_up_after_retries() {
+        block_on(async {
+            let (base, log) = serve(|_| Reply::new(429, "").header("Retry-After", "0")).await;
+            let f = fetcher();
+
+            let err = f.send(f.get(&format!("{base}/a"))).await.unwrap_err();
+            assert!(err.to_string().contains("429"), "{err}");
+            assert_eq!(log.lock().unwrap().len(), 1 + THROTTLE_RETRIES);
+        });
+    }
+
+    #[test]
+    fn throttled_request_is_retried_until_it_passes() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        block_on(async {
+            let hits = AtomicUsize::new(0);
+            let (base, log) = serve(move |_| match hits.fetch_add(1, Ordering::SeqCst) {
+                0 => Reply::new(503, "").header("Retry-After", "0"),
+                _ => Reply::new(200, "ok"),
+            })
+            .await;
+            let f = fetcher();
+
+            let resp = f.send(f.get(&format!("{base}/a"))).await.unwrap();
+            assert_eq!(resp.status(), StatusCode::OK);
+            assert_eq!(log.lock().unwrap().len(), 2);
+        });
+    }
+}
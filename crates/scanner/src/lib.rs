@@ -1,32 +1,72 @@
+pub mod auth;
 pub mod browser_manager;
+pub mod cdp;
+pub mod cdx;
 pub mod crawler;
+pub mod fetch;
 pub mod net;
+pub mod reanalyze;
+pub mod report;
+pub mod scan_config;
+pub mod scope;
+pub mod scope_import;
 pub mod screenshot;
+pub mod secrets;
+pub mod sources;
+pub mod verify;
+
+#[cfg(test)]
+mod test_server;
 
 use core::PathsLike;
-pub use crawler::process_single_url;
+pub use auth::{AuthPolicy, Cookie, Credentials};
+pub use browser_manager::{BrowserOptions, BROWSER_MANAGER};
+pub use cdx::{CdxFilter, CdxRecord, Snapshots};
+pub use crawler::{crawl, process_single_url, Frontier};
+pub use fetch::{FetchFailed, Fetched, Fetcher, RateLimit};
+pub use report::{ScanOutput, StatusLog, UrlRecord, UrlStatus};
+pub use scan_config::{Redirects, ScanConfig};
 pub use net::{fetch_live_or_wayback, fetch_wayback_urls};
+pub use reanalyze::{reanalyze, ReanalyzeStats};
+pub use scope::{Scope, ScopeRule};
+pub use scope_import::{AssetKind, ImportedScope, ScopeAsset};
 pub use screenshot::make_screenshot_task;
+pub use secrets::{findings_in, findings_in_url, scan_path, LocalScanStats};
+pub use sources::{
+    AlienVaultOtx, CommonCrawl, LocalFile, UrlScan, UrlSink, UrlSource, WaybackCdx, DEFAULT_MAX_PAGES,
+};
+pub use verify::{
+    verify_scan, GitHubVerifier, SecretVerifier, SendGridVerifier, SlackVerifier, StripeVerifier, Verifiers,
+    VerifyStats,
+};
 use anyhow::Result;
-use futures::{stream, StreamExt};
-use reqwest::Client;
 use std::{
-    collections::HashSet,
-    fs::{self, File},
+    fs,
     path::{Path, PathBuf},
-    sync::Arc,
 };
-use tokio::sync::Mutex;
+use futures::future::join_all;
 use core::utils::{extract_subdomains, read_urls};
 
 #[derive(Clone)]
 pub struct Paths {
     pub base: PathBuf,
     pub out_txt: PathBuf,
+    /// `URL<TAB>источник` для каждого найденного URL.
+    pub sources_tsv: PathBuf,
+    /// Записи Wayback CDX: URL, время, MIME, статус, digest, длина.
+    pub cdx_tsv: PathBuf,
     pub subdomains_txt: PathBuf,
     pub screenshots_dir: PathBuf,
     pub jsscripts_dir: PathBuf,
     pub sensitive_info_txt: PathBuf,
+    /// Находки по одной на строку (`core::findings::Finding`).
+    pub findings_jsonl: PathBuf,
+    /// Те же находки в SARIF 2.1.0.
+    pub findings_sarif: PathBuf,
+    /// Живой / только в архиве / недоступен для каждого загруженного URL.
+    pub url_status_csv: PathBuf,
+    /// Какой файл в assets/JSscripts из какого URL сохранён.
+    pub assets_tsv: PathBuf,
     pub assets_dir: PathBuf,
 }
 
@@ -44,10 +84,16 @@ impl Paths {
         Ok(Self {
             base: base.clone(),
             out_txt: base.join("out.txt"),
+            sources_tsv: base.join("sources.tsv"),
+            cdx_tsv: base.join(cdx::CDX_FILE),
             subdomains_txt: base.join("subdomains.txt"),
             screenshots_dir,
             jsscripts_dir,
             sensitive_info_txt: base.join("sensitive_info.txt"),
+            findings_jsonl: base.join("findings.jsonl"),
+            findings_sarif: base.join("findings.sarif"),
+            url_status_csv: base.join(report::URL_STATUS_FILE),
+            assets_tsv: base.join(report::ASSETS_FILE),
             assets_dir,
         })
     }
@@ -59,44 +105,81 @@ impl PathsLike for Paths {
     fn assets_dir(&self)      -> &Path { &self.assets_dir }
 }
 
+/// Скан домена с параметрами по умолчанию.
 pub async fn run_scan(domain: &str) -> Result<Paths, Box<dyn std::error::Error>> {
+    run_scan_with(ScanConfig::new(domain)).await
+}
+
+/// Скан домена: URL из `config.sources`, затем обход ссылок на `config.depth` переходов.
+/// Всё, что не проходит `config.scope`, не скачивается и не скриншотится;
+/// частота запросов к каждому хосту ограничена `config.rate_limit`.
+pub async fn run_scan_with(config: ScanConfig) -> Result<Paths, Box<dyn std::error::Error>> {
+    let domain = config.domain.as_str();
     let paths = Paths::new(domain)?;
-    let client = Client::new();
+    let fetcher = Fetcher::from_config(&config)?;
+    BROWSER_MANAGER.configure(config.browser_options())?;
 
-    let body = fetch_wayback_urls(&client, domain).await?;
-    fs::write(&paths.out_txt, &body)?;
+    // источники опрашиваются параллельно; отказ одного не останавливает скан
+    let sink = UrlSink::create(&paths.out_txt, &paths.sources_tsv)?;
+    let found = join_all(
+        config
+            .sources
+            .iter()
+            .map(|src| src.discover(&fetcher, domain, &sink)),
+    )
+    .await;
+    for (src, res) in config.sources.iter().zip(found) {
+        match res {
+            Ok(n) => println!("[+] {}: {} URL", src.name(), n),
+            Err(e) => eprintln!("[!] {}: {}", src.name(), e),
+        }
+    }
+    sink.flush()?;
+    println!("[+] Уникальных URL: {}", sink.len());
 
     let subdomains = extract_subdomains(&paths.out_txt).await?;
     if !subdomains.is_empty() {
         fs::write(&paths.subdomains_txt, subdomains.join("\n"))?;
     }
 
-    let info_file = Arc::new(Mutex::new(File::create(&paths.sensitive_info_txt)?));
+    let output = ScanOutput::create(&paths.sensitive_info_txt, &paths.findings_jsonl)?
+        .with_assets_log(&paths.assets_tsv)?;
 
     let mut urls = read_urls(&paths.out_txt).await?;
     urls.retain(|u| !u.trim().is_empty());
-    //let urls: Vec<String> = urls.into_iter().collect::<HashSet<_>>().into_iter().collect();
-    let urls: Vec<String> = urls
-        .into_iter()
-        .collect::<HashSet<String>>()   
-        .into_iter()
-        .collect();
-    
-    let concurrency = 4usize;
-    stream::iter(urls.into_iter().map(|url| {
-        let client = client.clone();
-        let info_file = Arc::clone(&info_file);
-        let paths = paths.clone();
-        async move {
-            if let Err(e) = process_single_url(&client, &url, &paths, &info_file).await {
-                eprintln!("Ошибка обработки {}: {}", url, e);
-            }
-            Ok::<(), Box<dyn std::error::Error>>(())
-        }
-    }))
-    .buffer_unordered(concurrency)
-    .collect::<Vec<_>>()
+
+    crawl(
+        &fetcher,
+        urls,
+        config.depth,
+        config.concurrency,
+        &config.scope,
+        &paths,
+        &output,
+    )
     .await;
 
+    let found = output
+        .finish_findings(&paths.findings_jsonl, &paths.findings_sarif)
+        .await?;
+    println!("[+] Находок: {found} ({})", paths.findings_sarif.display());
+
+    if let Some(verifiers) = &config.verifiers {
+        let v = verify_scan(&paths, &fetcher, verifiers).await?;
+        println!(
+            "[+] Проверка ключей: рабочих {}, отклонено {}, ошибок {}",
+            v.verified, v.rejected, v.errors
+        );
+    }
+
+    output.statuses.write_csv(&paths.url_status_csv)?;
+    println!(
+        "[+] Живых: {}, только в архиве: {}, недоступно: {} ({})",
+        output.statuses.count(UrlStatus::Live),
+        output.statuses.count(UrlStatus::ArchivedOnly),
+        output.statuses.count(UrlStatus::Gone),
+        paths.url_status_csv.display()
+    );
+
     Ok(paths)
 }
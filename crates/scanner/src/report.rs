@@ -0,0 +1,149 @@
+use anyhow::{Result as AnyResult, anyhow};
+use core::findings::{JsonlWriter, read_jsonl, write_sarif};
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+    sync::{Arc, Mutex as StdMutex},
+};
+use tokio::sync::Mutex;
+
+/// Имя файла в папке скана со статусами URL.
+pub const URL_STATUS_FILE: &str = "url_status.csv";
+
+/// Имя файла в папке скана: какой сохранённый файл из какого URL
+/// (`путь<TAB>URL<TAB>снимок`, `-` — без снимка).
+pub const ASSETS_FILE: &str = "assets.tsv";
+
+/// Чем закончилась загрузка URL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UrlStatus {
+    /// Живой сайт ответил 2xx.
+    Live,
+    /// Живой сайт не ответил или ответил ошибкой, но есть снимок в Wayback.
+    ArchivedOnly,
+    /// Нет ни живой страницы, ни снимка.
+    Gone,
+}
+
+impl UrlStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UrlStatus::Live => "live",
+            UrlStatus::ArchivedOnly => "archived_only",
+            UrlStatus::Gone => "gone",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct UrlRecord {
+    pub url: String,
+    pub status: UrlStatus,
+    /// Код ответа живого сайта; `None` — ошибка соединения или таймаут.
+    pub live_status: Option<u16>,
+    /// Откуда в итоге взято содержимое: сам URL или снимок.
+    pub final_url: Option<String>,
+}
+
+/// Статусы всех загруженных URL за скан.
+#[derive(Debug, Default)]
+pub struct StatusLog {
+    records: StdMutex<Vec<UrlRecord>>,
+}
+
+impl StatusLog {
+    pub fn record(&self, rec: UrlRecord) {
+        if let Ok(mut v) = self.records.lock() {
+            v.push(rec);
+        }
+    }
+
+    pub fn records(&self) -> Vec<UrlRecord> {
+        self.records.lock().map(|v| v.clone()).unwrap_or_default()
+    }
+
+    pub fn count(&self, status: UrlStatus) -> usize {
+        self.records
+            .lock()
+            .map(|v| v.iter().filter(|r| r.status == status).count())
+            .unwrap_or(0)
+    }
+
+    /// CSV `url,status,live_status,final_url`; сначала URL, живущие только в архиве.
+    pub fn write_csv(&self, path: &Path) -> AnyResult<()> {
+        let mut records = self.records();
+        let rank = |s: UrlStatus| match s {
+            UrlStatus::ArchivedOnly => 0,
+            UrlStatus::Gone => 1,
+            UrlStatus::Live => 2,
+        };
+        records.sort_by(|a, b| rank(a.status).cmp(&rank(b.status)).then(a.url.cmp(&b.url)));
+
+        let mut w = csv::Writer::from_path(path)
+            .map_err(|e| anyhow!("запись {}: {e}", path.display()))?;
+        w.write_record(["url", "status", "live_status", "final_url"])?;
+        for r in records {
+            let live = r.live_status.map(|c| c.to_string()).unwrap_or_default();
+            w.write_record([
+                r.url.as_str(),
+                r.status.as_str(),
+                live.as_str(),
+                r.final_url.as_deref().unwrap_or(""),
+            ])?;
+        }
+        w.flush()?;
+        Ok(())
+    }
+}
+
+/// Куда скан складывает результаты по ходу обхода.
+pub struct ScanOutput {
+    /// Находки правил в человекочитаемом виде (`sensitive_info.txt`).
+    pub info_file: Arc<Mutex<File>>,
+    /// Те же находки, по JSON-объекту `Finding` на строку.
+    pub findings: Mutex<JsonlWriter>,
+    pub statuses: StatusLog,
+    /// Журнал сохранённых файлов (`assets.tsv`), для `reanalyze`.
+    assets: Option<StdMutex<BufWriter<File>>>,
+}
+
+impl ScanOutput {
+    pub fn create(sensitive_info_txt: &Path, findings_jsonl: &Path) -> AnyResult<Self> {
+        Ok(Self {
+            info_file: Arc::new(Mutex::new(File::create(sensitive_info_txt)?)),
+            findings: Mutex::new(JsonlWriter::create(findings_jsonl)?),
+            statuses: StatusLog::default(),
+            assets: None,
+        })
+    }
+
+    /// Вести журнал сохранённых файлов в `assets_tsv`.
+    pub fn with_assets_log(mut self, assets_tsv: &Path) -> AnyResult<Self> {
+        let file = File::create(assets_tsv)
+            .map_err(|e| anyhow!("создание {}: {e}", assets_tsv.display()))?;
+        self.assets = Some(StdMutex::new(BufWriter::new(file)));
+        Ok(self)
+    }
+
+    /// Записать, из какого URL (и снимка) сохранён файл.
+    pub fn record_asset(&self, path: &Path, url: &str, snapshot: Option<&str>) {
+        let Some(assets) = &self.assets else { return };
+        if let Ok(mut w) = assets.lock() {
+            let _ = writeln!(w, "{}\t{}\t{}", path.display(), url, snapshot.unwrap_or("-"));
+        }
+    }
+
+    /// Дописать JSONL и собрать из него отчёт SARIF.
+    pub async fn finish_findings(&self, findings_jsonl: &Path, sarif: &Path) -> AnyResult<usize> {
+        self.findings.lock().await.flush()?;
+        if let Some(assets) = &self.assets {
+            if let Ok(mut w) = assets.lock() {
+                w.flush()?;
+            }
+        }
+        let all = read_jsonl(findings_jsonl)?;
+        write_sarif(&all, sarif)?;
+        Ok(all.len())
+    }
+}
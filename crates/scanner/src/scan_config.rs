@@ -0,0 +1,234 @@
+use crate::fetch::RateLimit;
+use crate::scope::Scope;
+
+use crate::auth::{Credentials, is_archive_url};
+use crate::browser_manager::BrowserOptions;
+use crate::cdx::Snapshots;
+use crate::sources::{UrlSource, WaybackCdx};
+use crate::verify::Verifiers;
+
+use anyhow::{Context, Result as AnyResult};
+use reqwest::{Certificate, Client, Proxy, redirect};
+use std::{fs, path::PathBuf, sync::Arc, time::Duration};
+
+pub(crate) const DEFAULT_USER_AGENT: &str = "curl/8.4.0";
+pub(crate) const DEFAULT_READ_TIMEOUT: Duration = Duration::from_secs(15);
+pub(crate) const DEFAULT_RETRIES: usize = 1;
+pub(crate) const DEFAULT_RETRY_BACKOFF: Duration = Duration::from_millis(500);
+
+/// Как обходиться с редиректами.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Redirects {
+    None,
+    Limit(usize),
+}
+
+/// Параметры скана: `ScanConfig::new(domain)` и цепочка сеттеров.
+#[derive(Debug, Clone)]
+pub struct ScanConfig {
+    pub domain: String,
+    /// Глубина обхода: 0 — только URL из Wayback.
+    pub depth: usize,
+    /// Сколько URL обрабатывается одновременно.
+    pub concurrency: usize,
+    pub connect_timeout: Duration,
+    /// Лимит на получение ответа при загрузке страницы.
+    pub read_timeout: Duration,
+    /// Повторы при таймауте или ошибке соединения.
+    pub retries: usize,
+    /// Пауза перед первым повтором, дальше удваивается.
+    pub retry_backoff: Duration,
+    pub user_agent: String,
+    pub redirects: Redirects,
+    pub scope: Scope,
+    pub rate_limit: RateLimit,
+    /// Вышестоящий прокси для HTTP-клиента и Chrome: `http://…`, `socks5://…`.
+    pub proxy: Option<String>,
+    /// CA-сертификат (PEM или DER), которому доверять, например сертификат Burp.
+    /// Только для HTTP-клиента: Chrome его не получает.
+    pub ca_cert: Option<PathBuf>,
+    /// Chrome не проверяет TLS-сертификаты вообще (для скриншотов через
+    /// перехватывающий прокси). Отдельно от `ca_cert`: это отключение проверки
+    /// для всех хостов, а не доверие одному CA.
+    pub browser_insecure: bool,
+    /// Заголовки, cookies и bearer-токен; уходят только на хосты из `scope`.
+    pub credentials: Credentials,
+    /// Откуда брать стартовые URL; по умолчанию — только Wayback CDX.
+    pub sources: Vec<Arc<dyn UrlSource>>,
+    /// Сколько исторических снимков из Wayback анализировать для каждого URL.
+    pub snapshots: Snapshots,
+    /// Проверять найденные ключи у провайдеров после скана; `None` — не проверять.
+    pub verifiers: Option<Verifiers>,
+}
+
+impl ScanConfig {
+    pub fn new(domain: &str) -> Self {
+        Self {
+            domain: domain.to_string(),
+            depth: 1,
+            concurrency: 4,
+            connect_timeout: Duration::from_secs(10),
+            read_timeout: DEFAULT_READ_TIMEOUT,
+            retries: DEFAULT_RETRIES,
+            retry_backoff: DEFAULT_RETRY_BACKOFF,
+            user_agent: DEFAULT_USER_AGENT.to_string(),
+            redirects: Redirects::Limit(10),
+            scope: Scope::for_domain(domain),
+            rate_limit: RateLimit::default(),
+            proxy: None,
+            ca_cert: None,
+            browser_insecure: false,
+            credentials: Credentials::default(),
+            sources: vec![Arc::new(WaybackCdx::default())],
+            snapshots: Snapshots::Latest,
+            verifiers: None,
+        }
+    }
+
+    pub fn depth(mut self, depth: usize) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    pub fn concurrency(mut self, n: usize) -> Self {
+        self.concurrency = n.max(1);
+        self
+    }
+
+    pub fn connect_timeout(mut self, t: Duration) -> Self {
+        self.connect_timeout = t;
+        self
+    }
+
+    pub fn read_timeout(mut self, t: Duration) -> Self {
+        self.read_timeout = t;
+        self
+    }
+
+    pub fn retries(mut self, n: usize) -> Self {
+        self.retries = n;
+        self
+    }
+
+    pub fn retry_backoff(mut self, base: Duration) -> Self {
+        self.retry_backoff = base;
+        self
+    }
+
+    pub fn user_agent(mut self, ua: &str) -> Self {
+        self.user_agent = ua.to_string();
+        self
+    }
+
+    pub fn redirects(mut self, policy: Redirects) -> Self {
+        self.redirects = policy;
+        self
+    }
+
+    pub fn scope(mut self, scope: Scope) -> Self {
+        self.scope = scope;
+        self
+    }
+
+    pub fn rate_limit(mut self, limits: RateLimit) -> Self {
+        self.rate_limit = limits;
+        self
+    }
+
+    pub fn proxy(mut self, url: &str) -> Self {
+        self.proxy = Some(url.to_string());
+        self
+    }
+
+    pub fn ca_cert(mut self, path: PathBuf) -> Self {
+        self.ca_cert = Some(path);
+        self
+    }
+
+    pub fn browser_insecure(mut self, on: bool) -> Self {
+        self.browser_insecure = on;
+        self
+    }
+
+    pub fn credentials(mut self, creds: Credentials) -> Self {
+        self.credentials = creds;
+        self
+    }
+
+    /// Заменить список источников URL.
+    pub fn sources(mut self, sources: Vec<Arc<dyn UrlSource>>) -> Self {
+        self.sources = sources;
+        self
+    }
+
+    /// Добавить источник URL к уже настроенным.
+    pub fn source(mut self, source: impl UrlSource + 'static) -> Self {
+        self.sources.push(Arc::new(source));
+        self
+    }
+
+    pub fn snapshots(mut self, mode: Snapshots) -> Self {
+        self.snapshots = mode;
+        self
+    }
+
+    pub fn verify(mut self, verifiers: Verifiers) -> Self {
+        self.verifiers = Some(verifiers);
+        self
+    }
+
+    /// Настройки Chrome для скриншотов.
+    pub fn browser_options(&self) -> BrowserOptions {
+        BrowserOptions {
+            proxy: self.proxy.clone(),
+            ignore_cert_errors: self.browser_insecure,
+        }
+    }
+
+    /// HTTP-клиент с таймаутами, User-Agent, редиректами, прокси и CA из конфига.
+    pub fn build_client(&self) -> AnyResult<Client> {
+        // редиректы за пределы скоупа не выполняются: ни запросов вне скоупа,
+        // ни пользовательских заголовков на чужих хостах
+        let limit = match self.redirects {
+            Redirects::None => 0,
+            Redirects::Limit(n) => n,
+        };
+        let scope = self.scope.clone();
+        let policy = redirect::Policy::custom(move |attempt| {
+            if attempt.previous().len() > limit {
+                return if limit == 0 {
+                    attempt.stop()
+                } else {
+                    attempt.error("слишком много редиректов")
+                };
+            }
+            let archive_hop =
+                is_archive_url(attempt.url()) && attempt.previous().last().is_some_and(is_archive_url);
+            if archive_hop || scope.allows(attempt.url().as_str()) {
+                attempt.follow()
+            } else {
+                attempt.stop()
+            }
+        });
+
+        let mut builder = Client::builder()
+            .connect_timeout(self.connect_timeout)
+            .user_agent(self.user_agent.as_str())
+            .redirect(policy);
+
+        if let Some(proxy) = &self.proxy {
+            builder = builder.proxy(Proxy::all(proxy).with_context(|| format!("прокси {proxy}"))?);
+        }
+
+        if let Some(path) = &self.ca_cert {
+            let raw = fs::read(path)
+                .with_context(|| format!("чтение CA-сертификата {}", path.display()))?;
+            let cert = Certificate::from_pem(&raw)
+                .or_else(|_| Certificate::from_der(&raw))
+                .with_context(|| format!("разбор CA-сертификата {}", path.display()))?;
+            builder = builder.add_root_certificate(cert);
+        }
+
+        Ok(builder.build()?)
+    }
+}
@@ -0,0 +1,294 @@
+use anyhow::{Context, Result as AnyResult, anyhow};
+use regex::{Regex, RegexBuilder};
+use std::{fs, net::IpAddr, path::Path};
+use url::{Host, Url};
+
+/// Одно правило скоупа.
+///
+/// Синтаксис:
+/// - `example.com`, `*.example.com`, `api-?.example.com` — glob по хосту;
+/// - `re:<regex>` — регулярка по полному URL;
+/// - `cidr:10.0.0.0/8`, `10.0.0.0/8`, `192.168.1.10` — диапазон адресов
+///   (сравнивается только хост-IP из URL, DNS не резолвится);
+/// - `/api/` — префикс пути.
+#[derive(Debug, Clone)]
+pub enum ScopeRule {
+    Host(Regex),
+    Regex(Regex),
+    Cidr(IpAddr, u8),
+    PathPrefix(String),
+}
+
+impl ScopeRule {
+    pub fn parse(raw: &str) -> AnyResult<Self> {
+        let s = raw.trim();
+        if s.is_empty() {
+            return Err(anyhow!("пустое правило скоупа"));
+        }
+
+        if let Some(re) = s.strip_prefix("re:") {
+            let re = Regex::new(re).with_context(|| format!("regex в правиле скоупа {s}"))?;
+            return Ok(Self::Regex(re));
+        }
+
+        if let Some(net) = s.strip_prefix("cidr:") {
+            let (ip, bits) = parse_cidr(net).ok_or_else(|| anyhow!("неверный CIDR: {net}"))?;
+            return Ok(Self::Cidr(ip, bits));
+        }
+
+        if s.starts_with('/') {
+            return Ok(Self::PathPrefix(s.to_string()));
+        }
+
+        if let Some((ip, bits)) = parse_cidr(s) {
+            return Ok(Self::Cidr(ip, bits));
+        }
+
+        Ok(Self::Host(host_glob(s)?))
+    }
+
+    fn is_path(&self) -> bool {
+        matches!(self, Self::PathPrefix(_))
+    }
+
+    fn matches(&self, url: &Url) -> bool {
+        match self {
+            Self::Host(re) => url.host_str().is_some_and(|h| re.is_match(h)),
+            Self::Regex(re) => re.is_match(url.as_str()),
+            Self::Cidr(net, bits) => {
+                let ip = match url.host() {
+                    Some(Host::Ipv4(v4)) => IpAddr::V4(v4),
+                    Some(Host::Ipv6(v6)) => IpAddr::V6(v6),
+                    _ => return false,
+                };
+                ip_in_net(ip, *net, *bits)
+            }
+            Self::PathPrefix(prefix) => url.path().starts_with(prefix.as_str()),
+        }
+    }
+}
+
+/// Набор правил in-scope / out-of-scope.
+///
+/// URL в скоупе, если он не попал ни под одно исключение и, при наличии
+/// соответствующих include-правил, совпал хотя бы с одним правилом хоста
+/// (glob/regex/CIDR) и хотя бы с одним префиксом пути.
+#[derive(Debug, Clone, Default)]
+pub struct Scope {
+    include: Vec<ScopeRule>,
+    exclude: Vec<ScopeRule>,
+}
+
+impl Scope {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Домен и все его поддомены.
+    pub fn for_domain(domain: &str) -> Self {
+        let mut scope = Self::new();
+        scope.include_domain(domain);
+        scope
+    }
+
+    /// Добавить в скоуп домен и все его поддомены.
+    pub fn include_domain(&mut self, domain: &str) {
+        let host = domain
+            .trim()
+            .trim_start_matches("http://")
+            .trim_start_matches("https://")
+            .trim_matches('/')
+            .trim_start_matches("*.");
+
+        for raw in [host.to_string(), format!("*.{host}")] {
+            if let Ok(rule) = ScopeRule::parse(&raw) {
+                self.include.push(rule);
+            }
+        }
+    }
+
+    pub fn include(&mut self, raw: &str) -> AnyResult<()> {
+        self.include.push(ScopeRule::parse(raw)?);
+        Ok(())
+    }
+
+    pub fn exclude(&mut self, raw: &str) -> AnyResult<()> {
+        self.exclude.push(ScopeRule::parse(raw)?);
+        Ok(())
+    }
+
+    /// Есть ли include-правила по хосту (glob, `re:`, `cidr:`). Одни префиксы
+    /// пути хосты не ограничивают: к ним нужно добавить домен цели.
+    pub fn has_host_includes(&self) -> bool {
+        self.include.iter().any(|r| !r.is_path())
+    }
+
+    /// Загрузить правила из файла: по одному на строку, `#` — комментарий,
+    /// `!` в начале строки — исключение.
+    pub fn load_file(&mut self, path: &Path) -> AnyResult<()> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("чтение файла скоупа {}", path.display()))?;
+
+        for (n, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let res = match line.strip_prefix('!') {
+                Some(rest) => self.exclude(rest),
+                None => self.include(line),
+            };
+            res.with_context(|| format!("{}:{}", path.display(), n + 1))?;
+        }
+        Ok(())
+    }
+
+    pub fn allows(&self, url: &str) -> bool {
+        let url = match Url::parse(url) {
+            Ok(u) => u,
+            Err(_) => return false,
+        };
+
+        if self.exclude.iter().any(|r| r.matches(&url)) {
+            return false;
+        }
+
+        let (paths, hosts): (Vec<&ScopeRule>, Vec<&ScopeRule>) =
+            self.include.iter().partition(|r| r.is_path());
+
+        (hosts.is_empty() || hosts.iter().any(|r| r.matches(&url)))
+            && (paths.is_empty() || paths.iter().any(|r| r.matches(&url)))
+    }
+}
+
+fn host_glob(pattern: &str) -> AnyResult<Regex> {
+    let mut re = String::from("^");
+    for c in pattern.chars() {
+        match c {
+            '*' => re.push_str(".*"),
+            '?' => re.push('.'),
+            c => re.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    re.push('$');
+
+    RegexBuilder::new(&re)
+        .case_insensitive(true)
+        .build()
+        .with_context(|| format!("glob хоста {pattern}"))
+}
+
+fn parse_cidr(s: &str) -> Option<(IpAddr, u8)> {
+    let (ip, bits) = match s.split_once('/') {
+        Some((ip, bits)) => (ip.parse::<IpAddr>().ok()?, Some(bits.parse::<u8>().ok()?)),
+        None => (s.parse::<IpAddr>().ok()?, None),
+    };
+
+    let max = if ip.is_ipv4() { 32 } else { 128 };
+    let bits = bits.unwrap_or(max);
+    if bits > max {
+        return None;
+    }
+    Some((ip, bits))
+}
+
+fn ip_in_net(ip: IpAddr, net: IpAddr, bits: u8) -> bool {
+    match (ip, net) {
+        (IpAddr::V4(a), IpAddr::V4(b)) => {
+            let mask = u32::MAX.checked_shl(32 - bits as u32).unwrap_or(0);
+            u32::from(a) & mask == u32::from(b) & mask
+        }
+        (IpAddr::V6(a), IpAddr::V6(b)) => {
+            let mask = u128::MAX.checked_shl(128 - bits as u32).unwrap_or(0);
+            u128::from(a) & mask == u128::from(b) & mask
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scope(include: &[&str], exclude: &[&str]) -> Scope {
+        let mut s = Scope::new();
+        for r in include {
+            s.include(r).unwrap();
+        }
+        for r in exclude {
+            s.exclude(r).unwrap();
+        }
+        s
+    }
+
+    #[test]
+    fn host_globs() {
+        let s = scope(&["*.example.com", "api-?.test.org"], &[]);
+        assert!(s.allows("https://www.example.com/"));
+        assert!(s.allows("https://a.b.EXAMPLE.com/x"));
+        assert!(!s.allows("https://example.com/"));
+        assert!(!s.allows("https://example.com.evil.net/"));
+        assert!(s.allows("http://api-1.test.org/"));
+        assert!(!s.allows("http://api-12.test.org/"));
+
+        let d = Scope::for_domain("https://example.com/");
+        assert!(d.allows("https://example.com/"));
+        assert!(d.allows("https://cdn.example.com/a.js"));
+        assert!(!d.allows("https://notexample.com/"));
+    }
+
+    #[test]
+    fn regex_matches_full_url() {
+        let s = scope(&[r"re:^https://[a-z]+\.example\.com/v\d/"], &[]);
+        assert!(s.allows("https://api.example.com/v2/users"));
+        assert!(!s.allows("http://api.example.com/v2/users"));
+        assert!(!s.allows("https://api.example.com/docs"));
+        assert!(ScopeRule::parse("re:(").is_err());
+    }
+
+    #[test]
+    fn cidr_matches_ip_hosts_only() {
+        let s = scope(&["cidr:10.0.0.0/8", "192.168.1.10", "2001:db8::/32"], &[]);
+        assert!(s.allows("http://10.20.30.40:8080/"));
+        assert!(!s.allows("http://11.0.0.1/"));
+        assert!(s.allows("http://192.168.1.10/"));
+        assert!(!s.allows("http://192.168.1.11/"));
+        assert!(s.allows("http://[2001:db8::1]/"));
+        assert!(!s.allows("http://ten.example.com/"));
+        assert!(ScopeRule::parse("cidr:10.0.0.0/33").is_err());
+    }
+
+    #[test]
+    fn path_prefix_narrows_hosts() {
+        let s = scope(&["example.com", "/api/"], &[]);
+        assert!(s.allows("https://example.com/api/users"));
+        assert!(!s.allows("https://example.com/admin"));
+        assert!(!s.allows("https://other.com/api/users"));
+    }
+
+    #[test]
+    fn path_only_includes_need_a_host_rule() {
+        let mut s = scope(&["/api/"], &[]);
+        assert!(!s.has_host_includes());
+        s.include_domain("example.com");
+        assert!(s.has_host_includes());
+        assert!(s.allows("https://example.com/api/x"));
+        assert!(!s.allows("https://evil.com/api/x"));
+    }
+
+    #[test]
+    fn exclude_wins_over_include() {
+        let s = scope(&["*.example.com", "example.com"], &["admin.example.com", "/logout", "re:\\.pdf$"]);
+        assert!(s.allows("https://www.example.com/"));
+        assert!(!s.allows("https://admin.example.com/"));
+        assert!(!s.allows("https://www.example.com/logout"));
+        assert!(!s.allows("https://example.com/files/report.pdf"));
+    }
+
+    #[test]
+    fn empty_scope_allows_everything_but_garbage() {
+        let s = Scope::new();
+        assert!(s.allows("https://anything.example/"));
+        assert!(!s.allows("not a url"));
+    }
+}
@@ -0,0 +1,412 @@
+use crate::scope::Scope;
+
+use anyhow::{Context, Result as AnyResult, anyhow};
+use serde_json::{Map, Value};
+use std::{collections::HashMap, fs, path::Path};
+use url::Url;
+
+/// Тип актива из выгрузки программы.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AssetKind {
+    Url,
+    Wildcard,
+    Cidr,
+    /// Мобильные приложения, исходники, железо и т.п. — сканеру не интересны.
+    Other,
+}
+
+#[derive(Debug, Clone)]
+pub struct ScopeAsset {
+    pub identifier: String,
+    pub kind: AssetKind,
+    pub in_scope: bool,
+}
+
+/// Скоуп программы bug bounty, импортированный из выгрузки HackerOne или Bugcrowd.
+#[derive(Debug, Clone, Default)]
+pub struct ImportedScope {
+    pub assets: Vec<ScopeAsset>,
+}
+
+impl ImportedScope {
+    /// Формат выбирается по расширению: `.csv` или `.json`.
+    pub fn load(path: &Path) -> AnyResult<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("чтение выгрузки скоупа {}", path.display()))?;
+
+        let ext = path
+            .extension()
+            .and_then(|s| s.to_str())
+            .unwrap_or("")
+            .to_ascii_lowercase();
+
+        match ext.as_str() {
+            "csv" => Self::from_csv(&text),
+            "json" => Self::from_json(&text),
+            _ => Err(anyhow!("неизвестный формат выгрузки: {}", path.display())),
+        }
+    }
+
+    /// CSV HackerOne (`identifier,asset_type,...,eligible_for_submission`)
+    /// и Bugcrowd (`target|name|uri, type|category, in_scope`).
+    pub fn from_csv(text: &str) -> AnyResult<Self> {
+        let mut rdr = csv::ReaderBuilder::new()
+            .flexible(true)
+            .from_reader(text.as_bytes());
+
+        let header: HashMap<String, usize> = rdr
+            .headers()?
+            .iter()
+            .enumerate()
+            // `In Scope` и `in_scope` — одна колонка
+            .map(|(i, h)| (h.trim().to_ascii_lowercase().replace(' ', "_"), i))
+            .collect();
+
+        let col = |names: &[&str]| names.iter().find_map(|n| header.get(*n).copied());
+        let id_col = col(&["identifier", "asset_identifier", "target", "uri", "url", "name"])
+            .ok_or_else(|| anyhow!("в CSV нет колонки с идентификатором актива"))?;
+        let kind_col = col(&["asset_type", "type", "category"]);
+        let scope_col = col(&["eligible_for_submission", "in_scope", "eligible"]);
+
+        let mut out = Self::default();
+        for rec in rdr.records() {
+            let rec = rec?;
+            let raw_id = rec.get(id_col).unwrap_or("");
+            let kind = kind_col.and_then(|i| rec.get(i)).unwrap_or("");
+            let in_scope = scope_col
+                .and_then(|i| rec.get(i))
+                .and_then(parse_flag)
+                .unwrap_or(true);
+            out.push_identifiers(raw_id, kind, in_scope);
+        }
+        Ok(out)
+    }
+
+    /// JSON API HackerOne (`data[].attributes`), API Bugcrowd (`target_groups[]`)
+    /// и дампы bounty-targets-data (`targets.in_scope` / `targets.out_of_scope`).
+    pub fn from_json(text: &str) -> AnyResult<Self> {
+        let v: Value = serde_json::from_str(text).context("разбор JSON выгрузки скоупа")?;
+        let mut out = Self::default();
+        out.walk_json(&v, None);
+        Ok(out)
+    }
+
+    fn walk_json(&mut self, v: &Value, ctx: Option<bool>) {
+        match v {
+            Value::Array(items) => {
+                for it in items {
+                    self.walk_json(it, ctx);
+                }
+            }
+            Value::Object(map) => {
+                if self.push_json_asset(map, ctx) {
+                    return;
+                }
+                let ctx = map.get("in_scope").and_then(Value::as_bool).or(ctx);
+                for (k, child) in map {
+                    let child_ctx = match k.as_str() {
+                        "in_scope" => Some(true),
+                        "out_of_scope" => Some(false),
+                        _ => ctx,
+                    };
+                    self.walk_json(child, child_ctx);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn push_json_asset(&mut self, map: &Map<String, Value>, ctx: Option<bool>) -> bool {
+        let get = |k: &str| {
+            map.get(k)
+                .and_then(Value::as_str)
+                .filter(|s| !s.trim().is_empty())
+        };
+
+        let id = get("asset_identifier")
+            .or_else(|| get("target"))
+            .or_else(|| map.get("category").and(get("uri").or_else(|| get("name"))));
+        let id = match id {
+            Some(v) => v,
+            None => return false,
+        };
+
+        let kind = get("asset_type")
+            .or_else(|| get("type"))
+            .or_else(|| get("category"))
+            .unwrap_or("");
+        let in_scope = map
+            .get("eligible_for_submission")
+            .and_then(Value::as_bool)
+            .or(ctx)
+            .unwrap_or(true);
+
+        self.push_identifiers(id, kind, in_scope);
+        true
+    }
+
+    fn push_identifiers(&mut self, raw: &str, kind: &str, in_scope: bool) {
+        // HackerOne иногда перечисляет несколько хостов в одном активе
+        for id in raw.split([',', ' ', '\n']).map(str::trim).filter(|s| !s.is_empty()) {
+            self.assets.push(ScopeAsset {
+                identifier: id.to_string(),
+                kind: classify(kind, id),
+                in_scope,
+            });
+        }
+    }
+
+    /// Цели для сканера: хосты in-scope веб-активов (у wildcard без `*.`).
+    pub fn targets(&self) -> Vec<String> {
+        let mut out: Vec<String> = self
+            .assets
+            .iter()
+            .filter(|a| a.in_scope && matches!(a.kind, AssetKind::Url | AssetKind::Wildcard))
+            .filter_map(|a| host_of(&a.identifier))
+            .map(|h| h.trim_start_matches("*.").to_string())
+            .filter(|h| !h.contains('*'))
+            .collect();
+        out.sort();
+        out.dedup();
+        out
+    }
+
+    /// Домены из in-scope wildcard-активов вида `*.example.com`.
+    pub fn wildcard_domains(&self) -> Vec<String> {
+        let mut out: Vec<String> = self
+            .assets
+            .iter()
+            .filter(|a| a.in_scope && a.kind == AssetKind::Wildcard)
+            .filter_map(|a| host_of(&a.identifier))
+            .filter_map(|h| h.strip_prefix("*.").map(str::to_string))
+            .filter(|h| !h.contains('*'))
+            .collect();
+        out.sort();
+        out.dedup();
+        out
+    }
+
+    /// Правила в формате файла скоупа (`--scope-file`).
+    pub fn to_scope_file(&self) -> String {
+        let mut out = String::from("# импортировано из выгрузки программы bug bounty\n");
+        for a in &self.assets {
+            for rule in scope_rules_for(a) {
+                if !a.in_scope {
+                    out.push('!');
+                }
+                out.push_str(&rule);
+                out.push('\n');
+            }
+        }
+        out
+    }
+
+    pub fn to_scope(&self) -> AnyResult<Scope> {
+        let mut scope = Scope::new();
+        for a in &self.assets {
+            for rule in scope_rules_for(a) {
+                if a.in_scope {
+                    scope.include(&rule)?;
+                } else {
+                    scope.exclude(&rule)?;
+                }
+            }
+        }
+        Ok(scope)
+    }
+}
+
+fn parse_flag(s: &str) -> Option<bool> {
+    match s.trim().to_ascii_lowercase().as_str() {
+        "true" | "yes" | "1" | "in scope" | "in_scope" => Some(true),
+        "false" | "no" | "0" | "out of scope" | "out_of_scope" => Some(false),
+        _ => None,
+    }
+}
+
+fn classify(kind: &str, id: &str) -> AssetKind {
+    let k = kind.trim().to_ascii_lowercase();
+
+    if is_cidr(id) {
+        return AssetKind::Cidr;
+    }
+    if !matches!(
+        k.as_str(),
+        "" | "url" | "wildcard" | "domain" | "website" | "web" | "api" | "other_url"
+    ) {
+        return AssetKind::Other;
+    }
+    match host_of(id) {
+        Some(h) if h.contains('*') => AssetKind::Wildcard,
+        Some(h) if h.contains('.') => AssetKind::Url,
+        _ => AssetKind::Other,
+    }
+}
+
+fn is_cidr(id: &str) -> bool {
+    let ip = id.split_once('/').map(|(ip, _)| ip).unwrap_or(id);
+    ip.parse::<std::net::IpAddr>().is_ok()
+}
+
+fn strip_scheme(id: &str) -> &str {
+    id.split_once("://").map(|(_, rest)| rest).unwrap_or(id)
+}
+
+fn host_of(id: &str) -> Option<String> {
+    let rest = strip_scheme(id);
+    let host = rest.split(['/', '?', '#']).next()?;
+    let host = host.rsplit_once(':').map(|(h, _)| h).unwrap_or(host);
+    if host.is_empty() || host.contains(char::is_whitespace) {
+        return None;
+    }
+    Some(host.to_ascii_lowercase())
+}
+
+/// Правила скоупа для актива. In-scope `*.example.com` покрывает и сам
+/// `example.com`: программы почти всегда имеют в виду и его; исключение
+/// `*.example.com` апекс не трогает.
+fn scope_rules_for(a: &ScopeAsset) -> Vec<String> {
+    match a.kind {
+        AssetKind::Wildcard => {
+            let Some(host) = host_of(&a.identifier) else {
+                return Vec::new();
+            };
+            match host.strip_prefix("*.") {
+                Some(apex) if a.in_scope && !apex.contains('*') => vec![apex.to_string(), host.clone()],
+                _ => vec![host],
+            }
+        }
+        _ => scope_rule_for(a).into_iter().collect(),
+    }
+}
+
+fn scope_rule_for(a: &ScopeAsset) -> Option<String> {
+    match a.kind {
+        AssetKind::Cidr => Some(format!("cidr:{}", a.identifier)),
+        AssetKind::Wildcard => host_of(&a.identifier),
+        AssetKind::Url => {
+            let host = host_of(&a.identifier)?;
+            let with_scheme = format!("https://{}", strip_scheme(&a.identifier));
+            let path = Url::parse(&with_scheme)
+                .ok()
+                .map(|u| u.path().trim_end_matches('/').to_string())
+                .unwrap_or_default();
+            if path.is_empty() {
+                Some(host)
+            } else {
+                Some(format!(
+                    "re:^https?://{}(:\\d+)?{}",
+                    regex::escape(&host),
+                    regex::escape(&path)
+                ))
+            }
+        }
+        AssetKind::Other => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(name: &str) -> ImportedScope {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata/scope").join(name);
+        ImportedScope::load(&path).unwrap()
+    }
+
+    fn asset<'a>(s: &'a ImportedScope, id: &str) -> &'a ScopeAsset {
+        s.assets
+            .iter()
+            .find(|a| a.identifier == id)
+            .unwrap_or_else(|| panic!("нет актива {id}: {:#?}", s.assets))
+    }
+
+    #[test]
+    fn hackerone_csv() {
+        let s = fixture("hackerone.csv");
+        assert_eq!(s.assets.len(), 7);
+        assert_eq!(asset(&s, "*.example.com").kind, AssetKind::Wildcard);
+        assert_eq!(asset(&s, "10.10.0.0/16").kind, AssetKind::Cidr);
+        assert_eq!(asset(&s, "com.example.android").kind, AssetKind::Other);
+        assert!(asset(&s, "pay.example.net").in_scope);
+        assert!(!asset(&s, "status.example.com").in_scope);
+
+        assert_eq!(
+            s.targets(),
+            ["app.example.org", "example.com", "pay.example.net", "shop.example.net"]
+        );
+        assert_eq!(s.wildcard_domains(), ["example.com"]);
+
+        let scope = s.to_scope().unwrap();
+        assert!(scope.allows("https://example.com/"));
+        assert!(scope.allows("https://www.example.com/"));
+        assert!(!scope.allows("https://status.example.com/"));
+        assert!(scope.allows("https://app.example.org/api/users"));
+        assert!(!scope.allows("https://app.example.org/admin"));
+        assert!(scope.allows("http://10.10.3.4/"));
+        assert!(!scope.allows("https://unrelated.com/"));
+    }
+
+    #[test]
+    fn bugcrowd_csv() {
+        let s = fixture("bugcrowd.csv");
+        assert_eq!(asset(&s, "*.acme.io").kind, AssetKind::Wildcard);
+        assert_eq!(asset(&s, "api.acme.io").kind, AssetKind::Url);
+        assert!(!asset(&s, "legacy.acme.io").in_scope);
+        assert_eq!(asset(&s, "iOS").kind, AssetKind::Other);
+        assert_eq!(s.targets(), ["acme.io", "api.acme.io"]);
+
+        let scope = s.to_scope().unwrap();
+        assert!(scope.allows("https://acme.io/"));
+        assert!(scope.allows("https://cdn.acme.io/"));
+        assert!(!scope.allows("https://legacy.acme.io/"));
+    }
+
+    #[test]
+    fn hackerone_api_json() {
+        let s = fixture("hackerone.json");
+        assert_eq!(s.assets.len(), 3);
+        assert!(!asset(&s, "admin.example.com").in_scope);
+        assert_eq!(asset(&s, "https://github.com/example/app").kind, AssetKind::Other);
+        assert_eq!(s.wildcard_domains(), ["example.com"]);
+        assert_eq!(
+            s.to_scope_file(),
+            "# импортировано из выгрузки программы bug bounty\nexample.com\n*.example.com\n!admin.example.com\n"
+        );
+    }
+
+    #[test]
+    fn bugcrowd_api_json() {
+        let s = fixture("bugcrowd.json");
+        assert!(asset(&s, "*.acme.io").in_scope);
+        assert_eq!(asset(&s, "https://api.acme.io/v2").kind, AssetKind::Url);
+        assert!(!asset(&s, "https://legacy.acme.io").in_scope);
+
+        let scope = s.to_scope().unwrap();
+        assert!(scope.allows("https://api.acme.io/v2/users"));
+        assert!(!scope.allows("https://legacy.acme.io/"));
+    }
+
+    #[test]
+    fn bounty_targets_dump() {
+        let s = fixture("bounty-targets.json");
+        assert_eq!(s.assets.len(), 4);
+        assert!(!asset(&s, "blog.example.com").in_scope);
+        assert!(!asset(&s, "*.internal.example.com").in_scope);
+        assert_eq!(asset(&s, "192.0.2.0/24").kind, AssetKind::Cidr);
+
+        let scope = s.to_scope().unwrap();
+        assert!(scope.allows("https://example.com/"));
+        assert!(!scope.allows("https://blog.example.com/"));
+        assert!(!scope.allows("https://a.internal.example.com/"));
+        // исключение `*.` не выкидывает апекс
+        assert!(scope.allows("https://internal.example.com/"));
+        assert!(scope.allows("http://192.0.2.7/"));
+    }
+
+    #[test]
+    fn unknown_extension_is_an_error() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("Cargo.toml");
+        assert!(ImportedScope::load(&path).is_err());
+    }
+}
@@ -1,12 +1,28 @@
 use anyhow::{Result as AnyResult, anyhow};
-use headless_chrome::protocol::page::ScreenshotFormat;
+use headless_chrome::{
+    Tab,
+    browser::tab::RequestInterceptionDecision,
+    protocol::{network::methods::RequestPattern, page::ScreenshotFormat},
+};
 use tokio::task;
-use std::{path::Path, thread, time::Duration};
+use std::{path::Path, sync::Arc, thread, time::Duration};
 use core::utils::sanitize_filename;
 
+use crate::auth::AuthPolicy;
 use crate::browser_manager::BROWSER_MANAGER;
+use crate::cdp::{CookieParam, SetCookies, SetExtraHttpHeaders, SetIgnoreCertificateErrors};
 
-pub async fn make_screenshot_task(url: &str, screenshots_dir: &Path) -> AnyResult<()> {
+/// `HTTP/1.1 204 No Content` в base64 — ответ вместо запросов вне скоупа.
+const OUT_OF_SCOPE_RESPONSE: &str = "SFRUUC8xLjEgMjA0IE5vIENvbnRlbnQNCg0K";
+
+/// Скриншот страницы. Если задан `auth`, cookies ставятся через CDP до навигации,
+/// а заголовки добавляются к запросам вкладки — и то и другое только для хостов из скоупа;
+/// с заголовками вкладка не загружает ничего вне скоупа.
+pub async fn make_screenshot_task(
+    url: &str,
+    screenshots_dir: &Path,
+    auth: Option<Arc<AuthPolicy>>,
+) -> AnyResult<()> {
     let fixed_url = url.to_string();
     let fixed_for_name = fixed_url.clone();
 
@@ -18,6 +34,15 @@ pub async fn make_screenshot_task(url: &str, screenshots_dir: &Path) -> AnyResul
 
             match browser.new_tab() {
                 Ok(tab) => {
+                    if BROWSER_MANAGER.options()?.ignore_cert_errors {
+                        tab.call_method(SetIgnoreCertificateErrors { ignore: true })
+                            .map_err(|e| anyhow!("setIgnoreCertificateErrors: {e}"))?;
+                    }
+
+                    if let Some(auth) = &auth {
+                        apply_auth(&tab, &fixed_url, auth)?;
+                    }
+
                     tab.navigate_to(&fixed_url)
                         .map_err(|e| anyhow!("navigate_to({fixed_url}): {e}"))?
                         .wait_until_navigated()
@@ -32,11 +57,11 @@ pub async fn make_screenshot_task(url: &str, screenshots_dir: &Path) -> AnyResul
                 }
                 Err(e) => {
                     let msg = e.to_string();
-                    if msg.contains("connection is closed") || msg.contains("WebSocket") {
-                        if attempt == 1 {
-                            let _ = BROWSER_MANAGER.invalidate();
-                            continue;
-                        }
+                    if (msg.contains("connection is closed") || msg.contains("WebSocket"))
+                        && attempt == 1
+                    {
+                        let _ = BROWSER_MANAGER.invalidate();
+                        continue;
                     }
                     return Err(anyhow!("Не удалось создать вкладку: {msg}"));
                 }
@@ -56,3 +81,57 @@ pub async fn make_screenshot_task(url: &str, screenshots_dir: &Path) -> AnyResul
     std::fs::write(&path, &data).map_err(|e| anyhow!("Запись файла {:?}: {e}", path))?;
     Ok(())
 }
+
+fn apply_auth(tab: &Tab, url: &str, auth: &Arc<AuthPolicy>) -> AnyResult<()> {
+    if let Some(parsed) = auth.applies_to(url) {
+        // host-only cookies: уйдут только на хост этой страницы
+        let cookies: Vec<CookieParam> = auth
+            .credentials()
+            .cookies_for(&parsed)
+            .map(|c| CookieParam {
+                name: c.name.clone(),
+                value: c.value.clone(),
+                url: url.to_string(),
+                path: c.path.clone(),
+                secure: c.secure,
+                http_only: c.http_only,
+                expires: (c.expires > 0).then_some(c.expires as f64),
+            })
+            .collect();
+        if !cookies.is_empty() {
+            tab.call_method(SetCookies { cookies })
+                .map_err(|e| anyhow!("Network.setCookies: {e}"))?;
+        }
+    }
+
+    let extra = auth.headers_for(url, false);
+    if extra.is_empty() {
+        return Ok(());
+    }
+
+    // headless_chrome 0.9 сам продолжает каждый перехваченный запрос и не даёт
+    // подменить в нём заголовки, поэтому они ставятся на всю вкладку, а запросы
+    // к хостам вне скоупа получают пустой ответ и до сети не доходят
+    tab.call_method(SetExtraHttpHeaders {
+        headers: extra.into_iter().collect(),
+    })
+    .map_err(|e| anyhow!("Network.setExtraHTTPHeaders: {e}"))?;
+
+    let auth = Arc::clone(auth);
+    let patterns = [RequestPattern {
+        url_pattern: Some("*"),
+        resource_type: None,
+        interception_stage: Some("Request"),
+    }];
+    tab.enable_request_interception(
+        &patterns,
+        Box::new(move |_transport, _session_id, ev| {
+            if auth.applies_to(&ev.request.url).is_some() {
+                RequestInterceptionDecision::Continue
+            } else {
+                RequestInterceptionDecision::Response(OUT_OF_SCOPE_RESPONSE.to_string())
+            }
+        }),
+    )
+    .map_err(|e| anyhow!("setRequestInterception: {e}"))
+}
@@ -0,0 +1,635 @@
+use crate::cdx::{self, CdxFilter, CDX_FILE, DEFAULT_PAGE_SIZE};
+use crate::fetch::Fetcher;
+use crate::net::normalize_host;
+
+use anyhow::{Context, Result as AnyResult, anyhow};
+use futures::future::BoxFuture;
+use reqwest::StatusCode;
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+use url::Url;
+
+/// Сколько страниц выдачи по умолчанию забирать у постраничных API.
+pub const DEFAULT_MAX_PAGES: usize = 20;
+
+/// Источник URL для домена.
+pub trait UrlSource: Send + Sync + Debug {
+    /// Короткое имя — тег в `sources.tsv`.
+    fn name(&self) -> &'static str;
+
+    /// Найти URL и передать их в `sink`. Возвращает число переданных URL.
+    fn discover<'a>(
+        &'a self,
+        fetcher: &'a Fetcher,
+        domain: &'a str,
+        sink: &'a UrlSink,
+    ) -> BoxFuture<'a, AnyResult<usize>>;
+}
+
+struct SinkState {
+    seen: HashMap<String, Vec<&'static str>>,
+    out: BufWriter<File>,
+    tags: BufWriter<File>,
+}
+
+/// Общий приёмник URL: дедуп в `out.txt`, пары `URL<TAB>источник` в `sources.tsv`.
+pub struct UrlSink {
+    dir: PathBuf,
+    state: Mutex<SinkState>,
+}
+
+impl UrlSink {
+    pub fn create(out_txt: &Path, sources_tsv: &Path) -> AnyResult<Self> {
+        Ok(Self {
+            dir: out_txt.parent().map(Path::to_path_buf).unwrap_or_default(),
+            state: Mutex::new(SinkState {
+                seen: HashMap::new(),
+                out: BufWriter::new(File::create(out_txt)?),
+                tags: BufWriter::new(File::create(sources_tsv)?),
+            }),
+        })
+    }
+
+    /// Добавить URL от источника `source`. `true` — URL встретился впервые.
+    pub fn push(&self, url: &str, source: &'static str) -> AnyResult<bool> {
+        let url = url.trim();
+        if url.is_empty() {
+            return Ok(false);
+        }
+
+        let mut st = self
+            .state
+            .lock()
+            .map_err(|e| anyhow!("mutex poisoned in UrlSink::push: {e}"))?;
+        let SinkState { seen, out, tags } = &mut *st;
+
+        let fresh = !seen.contains_key(url);
+        let by = seen.entry(url.to_string()).or_default();
+        if by.contains(&source) {
+            return Ok(false);
+        }
+        by.push(source);
+
+        if fresh {
+            writeln!(out, "{url}")?;
+        }
+        writeln!(tags, "{url}\t{source}")?;
+        Ok(fresh)
+    }
+
+    /// Папка скана: сюда источники кладут свои файлы (например, `cdx.tsv`).
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn len(&self) -> usize {
+        self.state.lock().map(|st| st.seen.len()).unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn flush(&self) -> AnyResult<()> {
+        let mut st = self
+            .state
+            .lock()
+            .map_err(|e| anyhow!("mutex poisoned in UrlSink::flush: {e}"))?;
+        st.out.flush()?;
+        st.tags.flush()?;
+        Ok(())
+    }
+}
+
+fn endpoint(base: &str, path: &str) -> AnyResult<Url> {
+    let full = format!("{}/{}", base.trim_end_matches('/'), path.trim_start_matches('/'));
+    Url::parse(&full).with_context(|| format!("неверный URL источника {full}"))
+}
+
+/// Выдача источника упёрлась в `max_pages`: остальные URL не загружены.
+fn warn_truncated(source: &str, max_pages: usize) {
+    eprintln!(
+        "[!] {source}: прочитано {max_pages} стр. выдачи, дальше не идём — часть URL \
+         могла не попасть в out.txt (поднять предел: --source-pages)"
+    );
+}
+
+async fn get_json(fetcher: &Fetcher, url: &Url) -> AnyResult<Option<Value>> {
+    let resp = fetcher.send(fetcher.get(url.as_str())).await?;
+    if resp.status() == StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    anyhow::ensure!(resp.status().is_success(), "{} -> {}", url, resp.status());
+    Ok(Some(resp.json().await?))
+}
+
+/// Wayback Machine CDX API. Записи целиком (время, MIME, статус, digest, длина)
+/// пишутся в `cdx.tsv` рядом с `out.txt`.
+#[derive(Debug, Clone)]
+pub struct WaybackCdx {
+    pub base_url: String,
+    pub filter: CdxFilter,
+    /// Записей на страницу выдачи.
+    pub page_size: usize,
+}
+
+impl Default for WaybackCdx {
+    fn default() -> Self {
+        Self {
+            base_url: "https://web.archive.org".to_string(),
+            filter: CdxFilter::default(),
+            page_size: DEFAULT_PAGE_SIZE,
+        }
+    }
+}
+
+impl UrlSource for WaybackCdx {
+    fn name(&self) -> &'static str {
+        "wayback"
+    }
+
+    fn discover<'a>(
+        &'a self,
+        fetcher: &'a Fetcher,
+        domain: &'a str,
+        sink: &'a UrlSink,
+    ) -> BoxFuture<'a, AnyResult<usize>> {
+        Box::pin(async move {
+            let path = sink.dir().join(CDX_FILE);
+            let mut records = BufWriter::new(
+                File::create(&path).with_context(|| format!("создание {}", path.display()))?,
+            );
+
+            let n = cdx::list_domain(
+                fetcher,
+                &self.base_url,
+                &normalize_host(domain),
+                &self.filter,
+                self.page_size,
+                |rec| {
+                    writeln!(records, "{}", rec.to_tsv())?;
+                    sink.push(&rec.original, self.name())?;
+                    Ok(())
+                },
+            )
+            .await?;
+
+            records.flush()?;
+            Ok(n)
+        })
+    }
+}
+
+/// Индекс Common Crawl. Без `index` берётся самый свежий из `collinfo.json`.
+#[derive(Debug, Clone)]
+pub struct CommonCrawl {
+    pub base_url: String,
+    pub index: Option<String>,
+    /// Сколько страниц выдачи читать максимум.
+    pub max_pages: usize,
+}
+
+impl Default for CommonCrawl {
+    fn default() -> Self {
+        Self {
+            base_url: "https://index.commoncrawl.org".to_string(),
+            index: None,
+            max_pages: DEFAULT_MAX_PAGES,
+        }
+    }
+}
+
+impl UrlSource for CommonCrawl {
+    fn name(&self) -> &'static str {
+        "commoncrawl"
+    }
+
+    fn discover<'a>(
+        &'a self,
+        fetcher: &'a Fetcher,
+        domain: &'a str,
+        sink: &'a UrlSink,
+    ) -> BoxFuture<'a, AnyResult<usize>> {
+        Box::pin(async move {
+            let index = match &self.index {
+                Some(i) => i.clone(),
+                None => {
+                    let info = get_json(fetcher, &endpoint(&self.base_url, "collinfo.json")?)
+                        .await?
+                        .ok_or_else(|| anyhow!("Common Crawl: нет collinfo.json"))?;
+                    info.get(0)
+                        .and_then(|c| c.get("id"))
+                        .and_then(Value::as_str)
+                        .ok_or_else(|| anyhow!("Common Crawl: пустой список индексов"))?
+                        .to_string()
+                }
+            };
+
+            let mut n = 0;
+            let mut more = true;
+            for page in 0..self.max_pages {
+                let mut q = endpoint(&self.base_url, &format!("{index}-index"))?;
+                q.set_query(Some(&format!(
+                    "url=*.{}&output=json&fl=url&page={page}",
+                    normalize_host(domain)
+                )));
+
+                let resp = fetcher.send(fetcher.get(q.as_str())).await?;
+                // за последней страницей индекс отвечает 400/404
+                if !resp.status().is_success() {
+                    more = false;
+                    break;
+                }
+                let body = resp.text().await?;
+                if body.trim().is_empty() {
+                    more = false;
+                    break;
+                }
+                for line in body.lines() {
+                    let url = serde_json::from_str::<Value>(line)
+                        .ok()
+                        .and_then(|v| v.get("url").and_then(Value::as_str).map(str::to_string));
+                    if let Some(url) = url {
+                        sink.push(&url, self.name())?;
+                        n += 1;
+                    }
+                }
+            }
+            if more {
+                warn_truncated(self.name(), self.max_pages);
+            }
+            Ok(n)
+        })
+    }
+}
+
+/// AlienVault OTX: список URL, известных для домена.
+#[derive(Debug, Clone)]
+pub struct AlienVaultOtx {
+    pub base_url: String,
+    /// Сколько страниц выдачи читать максимум.
+    pub max_pages: usize,
+}
+
+impl Default for AlienVaultOtx {
+    fn default() -> Self {
+        Self {
+            base_url: "https://otx.alienvault.com".to_string(),
+            max_pages: DEFAULT_MAX_PAGES,
+        }
+    }
+}
+
+impl UrlSource for AlienVaultOtx {
+    fn name(&self) -> &'static str {
+        "otx"
+    }
+
+    fn discover<'a>(
+        &'a self,
+        fetcher: &'a Fetcher,
+        domain: &'a str,
+        sink: &'a UrlSink,
+    ) -> BoxFuture<'a, AnyResult<usize>> {
+        Box::pin(async move {
+            let mut n = 0;
+            let mut more = true;
+            for page in 1..=self.max_pages {
+                let mut q = endpoint(
+                    &self.base_url,
+                    &format!("api/v1/indicators/domain/{}/url_list", normalize_host(domain)),
+                )?;
+                q.set_query(Some(&format!("limit=500&page={page}")));
+
+                let v = match get_json(fetcher, &q).await? {
+                    Some(v) => v,
+                    None => {
+                        more = false;
+                        break;
+                    }
+                };
+                let list = v.get("url_list").and_then(Value::as_array);
+                for url in list.into_iter().flatten().filter_map(|e| e.get("url")?.as_str()) {
+                    sink.push(url, self.name())?;
+                    n += 1;
+                }
+                if !v.get("has_next").and_then(Value::as_bool).unwrap_or(false) {
+                    more = false;
+                    break;
+                }
+            }
+            if more {
+                warn_truncated(self.name(), self.max_pages);
+            }
+            Ok(n)
+        })
+    }
+}
+
+/// Поиск urlscan.io по домену; без ключа API выдача урезана.
+#[derive(Debug, Clone)]
+pub struct UrlScan {
+    pub base_url: String,
+    pub api_key: Option<String>,
+    /// Сколько страниц выдачи читать максимум.
+    pub max_pages: usize,
+}
+
+impl Default for UrlScan {
+    fn default() -> Self {
+        Self {
+            base_url: "https://urlscan.io".to_string(),
+            api_key: None,
+            max_pages: DEFAULT_MAX_PAGES,
+        }
+    }
+}
+
+impl UrlSource for UrlScan {
+    fn name(&self) -> &'static str {
+        "urlscan"
+    }
+
+    fn discover<'a>(
+        &'a self,
+        fetcher: &'a Fetcher,
+        domain: &'a str,
+        sink: &'a UrlSink,
+    ) -> BoxFuture<'a, AnyResult<usize>> {
+        Box::pin(async move {
+            let mut n = 0;
+            let mut search_after: Option<String> = None;
+            let mut more = true;
+
+            for _ in 0..self.max_pages {
+                let mut q = endpoint(&self.base_url, "api/v1/search/")?;
+                q.query_pairs_mut()
+                    .append_pair("q", &format!("domain:{}", normalize_host(domain)))
+                    .append_pair("size", "1000");
+                if let Some(after) = &search_after {
+                    q.query_pairs_mut().append_pair("search_after", after);
+                }
+
+                let mut rb = fetcher.get(q.as_str());
+                if let Some(key) = &self.api_key {
+                    rb = rb.header("API-Key", key);
+                }
+                let resp = fetcher.send(rb).await?;
+                anyhow::ensure!(resp.status().is_success(), "{} -> {}", q, resp.status());
+                let v: Value = resp.json().await?;
+
+                let results = v.get("results").and_then(Value::as_array).cloned().unwrap_or_default();
+                for r in &results {
+                    for key in ["page", "task"] {
+                        if let Some(url) = r.get(key).and_then(|p| p.get("url")).and_then(Value::as_str) {
+                            sink.push(url, self.name())?;
+                            n += 1;
+                        }
+                    }
+                }
+
+                let has_more = v.get("has_more").and_then(Value::as_bool).unwrap_or(false);
+                search_after = results
+                    .last()
+                    .and_then(|r| r.get("sort"))
+                    .and_then(Value::as_array)
+                    .map(|s| {
+                        s.iter()
+                            .map(|x| x.as_str().map(str::to_string).unwrap_or_else(|| x.to_string()))
+                            .collect::<Vec<_>>()
+                            .join(",")
+                    });
+                if !has_more || search_after.is_none() {
+                    more = false;
+                    break;
+                }
+            }
+            if more {
+                warn_truncated(self.name(), self.max_pages);
+            }
+            Ok(n)
+        })
+    }
+}
+
+/// Локальный файл со списком URL (по одному на строку).
+#[derive(Debug, Clone)]
+pub struct LocalFile {
+    pub path: PathBuf,
+}
+
+impl UrlSource for LocalFile {
+    fn name(&self) -> &'static str {
+        "file"
+    }
+
+    fn discover<'a>(
+        &'a self,
+        _fetcher: &'a Fetcher,
+        _domain: &'a str,
+        sink: &'a UrlSink,
+    ) -> BoxFuture<'a, AnyResult<usize>> {
+        Box::pin(async move {
+            let text = fs::read_to_string(&self.path)
+                .with_context(|| format!("чтение {}", self.path.display()))?;
+            let mut n = 0;
+            for line in text.lines().map(str::trim).filter(|l| !l.is_empty()) {
+                sink.push(line, self.name())?;
+                n += 1;
+            }
+            Ok(n)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server::{block_on, fetcher, serve, temp_dir, Reply};
+    use serde_json::json;
+
+    fn sink(name: &str) -> (UrlSink, PathBuf) {
+        let dir = temp_dir(name);
+        let sink = UrlSink::create(&dir.join("out.txt"), &dir.join("sources.tsv")).unwrap();
+        (sink, dir)
+    }
+
+    fn lines(path: &Path) -> Vec<String> {
+        fs::read_to_string(path).unwrap().lines().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn commoncrawl_picks_latest_index_and_pages_until_error() {
+        block_on(async {
+            let (base, log) = serve(|req| match (req.path(), req.query("page").as_deref()) {
+                ("/collinfo.json", _) => Reply::new(200, r#"[{"id":"CC-MAIN-2024-33"},{"id":"CC-MAIN-2024-30"}]"#),
+                ("/CC-MAIN-2024-33-index", Some("0")) => Reply::new(
+                    200,
+                    "{\"url\":\"https://ex.com/a\"}\n{\"url\":\"https://ex.com/b\"}\n",
+                ),
+                ("/CC-MAIN-2024-33-index", Some("1")) => Reply::new(200, "{\"url\":\"https://ex.com/c\"}\n"),
+                _ => Reply::new(400, "no more pages"),
+            })
+            .await;
+            let src = CommonCrawl {
+                base_url: base,
+                ..CommonCrawl::default()
+            };
+            let (sink, dir) = sink("cc");
+
+            let n = src.discover(&fetcher(), "ex.com", &sink).await.unwrap();
+            sink.flush().unwrap();
+
+            assert_eq!(n, 3);
+            assert_eq!(lines(&dir.join("out.txt")), ["https://ex.com/a", "https://ex.com/b", "https://ex.com/c"]);
+            let log = log.lock().unwrap();
+            let pages: Vec<_> = log.iter().filter_map(|r| r.query("page")).collect();
+            assert_eq!(pages, ["0", "1", "2"]);
+            assert_eq!(log[1].query("url").as_deref(), Some("*.ex.com"));
+        });
+    }
+
+    #[test]
+    fn otx_follows_has_next() {
+        block_on(async {
+            let (base, log) = serve(|req| {
+                assert_eq!(req.method, "GET");
+                assert_eq!(req.path(), "/api/v1/indicators/domain/ex.com/url_list");
+                let body = match req.query("page").as_deref() {
+                    Some("1") => json!({"url_list": [{"url": "https://ex.com/1"}, {"url": "https://ex.com/2"}], "has_next": true}),
+                    Some("2") => json!({"url_list": [{"url": "https://ex.com/3"}], "has_next": false}),
+                    _ => json!({"url_list": [{"url": "https://ex.com/never"}], "has_next": false}),
+                };
+                Reply::new(200, body.to_string())
+            })
+            .await;
+            let (sink, dir) = sink("otx");
+
+            let n = AlienVaultOtx {
+                base_url: base,
+                ..AlienVaultOtx::default()
+            }
+            .discover(&fetcher(), "ex.com", &sink)
+            .await
+            .unwrap();
+            sink.flush().unwrap();
+
+            assert_eq!(n, 3);
+            assert_eq!(log.lock().unwrap().len(), 2);
+            assert_eq!(lines(&dir.join("sources.tsv"))[2], "https://ex.com/3\totx");
+        });
+    }
+
+    #[test]
+    fn otx_missing_domain_is_empty() {
+        block_on(async {
+            let (base, _) = serve(|_| Reply::new(404, "")).await;
+            let (sink, _) = sink("otx-404");
+            let n = AlienVaultOtx {
+                base_url: base,
+                ..AlienVaultOtx::default()
+            }
+            .discover(&fetcher(), "ex.com", &sink)
+            .await
+            .unwrap();
+            assert_eq!(n, 0);
+        });
+    }
+
+    #[test]
+    fn otx_stops_at_max_pages() {
+        block_on(async {
+            let (base, log) = serve(|req| {
+                let page = req.query("page").unwrap_or_default();
+                let body = json!({"url_list": [{"url": format!("https://ex.com/{page}")}], "has_next": true});
+                Reply::new(200, body.to_string())
+            })
+            .await;
+            let (sink, _) = sink("otx-cap");
+
+            let n = AlienVaultOtx {
+                base_url: base,
+                max_pages: 3,
+            }
+            .discover(&fetcher(), "ex.com", &sink)
+            .await
+            .unwrap();
+
+            assert_eq!(n, 3);
+            assert_eq!(log.lock().unwrap().len(), 3);
+        });
+    }
+
+    #[test]
+    fn urlscan_pages_with_search_after() {
+        block_on(async {
+            let (base, log) = serve(|req| {
+                let body = match req.query("search_after").as_deref() {
+                    None => json!({
+                        "results": [
+                            {"page": {"url": "https://ex.com/p1"}, "task": {"url": "https://ex.com/t1"}, "sort": [1700000000000u64, "aaa"]},
+                            {"page": {"url": "https://ex.com/p2"}, "sort": [1690000000000u64, "bbb"]},
+                        ],
+                        "has_more": true,
+                    }),
+                    Some("1690000000000,bbb") => json!({
+                        "results": [{"page": {"url": "https://ex.com/p3"}, "sort": [1680000000000u64, "ccc"]}],
+                        "has_more": false,
+                    }),
+                    Some(other) => panic!("неожиданный search_after {other}"),
+                };
+                Reply::new(200, body.to_string())
+            })
+            .await;
+            let src = UrlScan {
+                base_url: base,
+                api_key: Some("k3y".to_string()),
+                ..UrlScan::default()
+            };
+            let (sink, _) = sink("urlscan");
+
+            let n = src.discover(&fetcher(), "ex.com", &sink).await.unwrap();
+
+            assert_eq!(n, 4);
+            assert_eq!(sink.len(), 4);
+            let log = log.lock().unwrap();
+            assert_eq!(log.len(), 2);
+            assert_eq!(log[0].query("q").as_deref(), Some("domain:ex.com"));
+            assert_eq!(log[0].header("API-Key"), Some("k3y"));
+        });
+    }
+
+    #[test]
+    fn wayback_writes_cdx_records_and_urls() {
+        block_on(async {
+            let (base, _) = serve(|_| {
+                Reply::new(
+                    200,
+                    "https://ex.com/app.js 20200101000000 application/javascript 200 AAA 1234\n\
+                     https://ex.com/ 20210101000000 text/html 200 BBB -\n",
+                )
+            })
+            .await;
+            let src = WaybackCdx {
+                base_url: base,
+                ..WaybackCdx::default()
+            };
+            let (sink, dir) = sink("wayback");
+
+            let n = src.discover(&fetcher(), "ex.com", &sink).await.unwrap();
+            sink.flush().unwrap();
+
+            assert_eq!(n, 2);
+            let cdx = lines(&dir.join(CDX_FILE));
+            assert_eq!(cdx[0], "https://ex.com/app.js\t20200101000000\tapplication/javascript\t200\tAAA\t1234");
+            assert_eq!(lines(&dir.join("out.txt")), ["https://ex.com/app.js", "https://ex.com/"]);
+        });
+    }
+}
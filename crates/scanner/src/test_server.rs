@@ -0,0 +1,190 @@
+//! Заглушка HTTP-сервера для тестов источников и проверяльщиков: отвечает
+//! обработчиком на каждый запрос и запоминает, что пришло.
+
+use crate::fetch::{Fetcher, RateLimit};
+use crate::scan_config::ScanConfig;
+
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+use url::Url;
+
+/// Запрос, как его увидел сервер.
+#[derive(Debug, Clone)]
+pub(crate) struct Request {
+    pub method: String,
+    /// Путь с запросом: `/api/v1/search/?q=…`.
+    pub target: String,
+    /// Имена — в нижнем регистре.
+    pub headers: HashMap<String, String>,
+}
+
+impl Request {
+    pub fn path(&self) -> &str {
+        self.target.split('?').next().unwrap_or("")
+    }
+
+    /// Значение параметра запроса (раскодированное).
+    pub fn query(&self, key: &str) -> Option<String> {
+        let url = Url::parse(&format!("http://stub{}", self.target)).ok()?;
+        url.query_pairs().find(|(k, _)| k == key).map(|(_, v)| v.into_owned())
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(&name.to_ascii_lowercase()).map(String::as_str)
+    }
+}
+
+/// Ответ заглушки.
+pub(crate) struct Reply {
+    pub status: u16,
+    pub body: String,
+    /// Пауза перед ответом — чтобы проверить таймауты.
+    pub delay: Duration,
+    /// Дополнительные заголовки ответа.
+    pub headers: Vec<(String, String)>,
+}
+
+impl Reply {
+    pub fn new(status: u16, body: impl Into<String>) -> Self {
+        Self {
+            status,
+            body: body.into(),
+            delay: Duration::ZERO,
+            headers: Vec::new(),
+        }
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn delayed(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+}
+
+pub(crate) type Log = Arc<Mutex<Vec<Request>>>;
+
+/// Поднять сервер на случайном порту. Возвращает базовый URL и журнал запросов.
+pub(crate) async fn serve<F>(handler: F) -> (String, Log)
+where
+    F: Fn(&Request) -> Reply + Send + Sync + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind");
+    let base = format!("http://{}", listener.local_addr().expect("addr"));
+    let log: Log = Arc::default();
+    let handler = Arc::new(handler);
+
+    let seen = log.clone();
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let handler = handler.clone();
+            let seen = seen.clone();
+            tokio::spawn(async move {
+                let _ = handle(stream, &*handler, &seen).await;
+            });
+        }
+    });
+    (base, log)
+}
+
+async fn handle<F>(mut stream: TcpStream, handler: &F, log: &Log) -> std::io::Result<()>
+where
+    F: Fn(&Request) -> Reply,
+{
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 4096];
+    let head_end = loop {
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            return Ok(());
+        }
+        buf.extend_from_slice(&chunk[..n]);
+        if let Some(i) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break i + 4;
+        }
+    };
+
+    let head = String::from_utf8_lossy(&buf[..head_end]).to_string();
+    let mut lines = head.lines();
+    let mut first = lines.next().unwrap_or("").split_whitespace();
+    let method = first.next().unwrap_or("").to_string();
+    let target = first.next().unwrap_or("").to_string();
+    let headers: HashMap<String, String> = lines
+        .filter_map(|l| l.split_once(':'))
+        .map(|(k, v)| (k.trim().to_ascii_lowercase(), v.trim().to_string()))
+        .collect();
+
+    // тело читаем до конца, чтобы клиент не получил сброс соединения
+    let len: usize = headers.get("content-length").and_then(|v| v.parse().ok()).unwrap_or(0);
+    while buf.len() < head_end + len {
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            break;
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    }
+
+    let req = Request {
+        method,
+        target,
+        headers,
+    };
+    let reply = handler(&req);
+    if let Ok(mut v) = log.lock() {
+        v.push(req);
+    }
+    if !reply.delay.is_zero() {
+        tokio::time::sleep(reply.delay).await;
+    }
+
+    let mut extra = String::new();
+    for (k, v) in &reply.headers {
+        extra.push_str(&format!("{k}: {v}\r\n"));
+    }
+    let resp = format!(
+        "HTTP/1.1 {} Stub\r\nContent-Type: application/json\r\nContent-Length: {}\r\n{extra}Connection: close\r\n\r\n{}",
+        reply.status,
+        reply.body.len(),
+        reply.body
+    );
+    stream.write_all(resp.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+/// Выполнить асинхронный тест. `#[tokio::test]` не подходит: макрос ссылается
+/// на `core::…`, а в этом workspace `core` — наш крейт.
+pub(crate) fn block_on<F: std::future::Future>(f: F) -> F::Output {
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .expect("runtime")
+        .block_on(f)
+}
+
+/// Клиент без лимитов и повторов, с коротким таймаутом ответа.
+pub(crate) fn fetcher() -> Fetcher {
+    let cfg = ScanConfig::new("127.0.0.1")
+        .rate_limit(RateLimit::unlimited())
+        .retries(0)
+        .read_timeout(Duration::from_millis(500));
+    Fetcher::from_config(&cfg).expect("fetcher")
+}
+
+/// Пустая временная папка под тест.
+pub(crate) fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("webhound-{name}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).expect("temp dir");
+    dir
+}
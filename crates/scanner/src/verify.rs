@@ -0,0 +1,501 @@
+use crate::fetch::Fetcher;
+use crate::Paths;
+
+use anyhow::{Context, Result as AnyResult, anyhow};
+use core::findings::{Finding, Verification, VerifyStatus, read_jsonl, JsonlWriter, write_sarif};
+use futures::future::BoxFuture;
+use reqwest::{header::AUTHORIZATION, RequestBuilder, StatusCode};
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    fs::OpenOptions,
+    io::Write,
+    sync::Arc,
+};
+
+/// Живая проверка секрета у провайдера.
+pub trait SecretVerifier: Send + Sync + Debug {
+    /// Короткое имя — ключ для `--verify-base`.
+    fn name(&self) -> &'static str;
+
+    /// `id` правил, находки которых проверяет.
+    fn rule_ids(&self) -> &'static [&'static str];
+
+    /// Проверить секрет находки. `Err` — ответ не удалось получить или понять.
+    fn verify<'a>(
+        &'a self,
+        fetcher: &'a Fetcher,
+        finding: &'a Finding,
+    ) -> BoxFuture<'a, AnyResult<Verification>>;
+}
+
+fn endpoint(base: &str, path: &str) -> String {
+    format!("{}/{}", base.trim_end_matches('/'), path.trim_start_matches('/'))
+}
+
+/// Отправить запрос и забрать статус с телом. Провайдер, который не отвечает
+/// дольше `--timeout`, даёт ошибку, а не подвешивает проверку.
+async fn call(fetcher: &Fetcher, rb: RequestBuilder) -> AnyResult<(StatusCode, String)> {
+    let resp = fetcher.send(rb.timeout(fetcher.read_timeout())).await?;
+    let status = resp.status();
+    Ok((status, resp.text().await.unwrap_or_default()))
+}
+
+fn unexpected(status: StatusCode) -> anyhow::Error {
+    anyhow!("неожиданный ответ {status}")
+}
+
+fn json_str(body: &str, key: &str) -> String {
+    serde_json::from_str::<Value>(body)
+        .ok()
+        .and_then(|v| v.get(key).and_then(Value::as_str).map(str::to_string))
+        .unwrap_or_default()
+}
+
+/// Токены GitHub: `GET /user`.
+#[derive(Debug, Clone)]
+pub struct GitHubVerifier {
+    pub base_url: String,
+}
+
+impl Default for GitHubVerifier {
+    fn default() -> Self {
+        Self {
+            base_url: "https://api.github.com".to_string(),
+        }
+    }
+}
+
+impl SecretVerifier for GitHubVerifier {
+    fn name(&self) -> &'static str {
+        "github"
+    }
+
+    fn rule_ids(&self) -> &'static [&'static str] {
+        &["github-pat", "github-fine-grained-pat", "github-oauth", "github-app-token"]
+    }
+
+    fn verify<'a>(
+        &'a self,
+        fetcher: &'a Fetcher,
+        finding: &'a Finding,
+    ) -> BoxFuture<'a, AnyResult<Verification>> {
+        Box::pin(async move {
+            let rb = fetcher
+                .client()
+                .get(endpoint(&self.base_url, "/user"))
+                .header(AUTHORIZATION, format!("token {}", finding.secret))
+                .header("Accept", "application/vnd.github+json");
+            match call(fetcher, rb).await? {
+                (s, body) if s.is_success() => {
+                    Ok(Verification::new(VerifyStatus::Verified, json_str(&body, "login")))
+                }
+                (StatusCode::UNAUTHORIZED, _) => Ok(Verification::new(VerifyStatus::Rejected, "401")),
+                (s, _) => Err(unexpected(s)),
+            }
+        })
+    }
+}
+
+/// Токены Slack: `POST /api/auth.test`, ответ — `{"ok": …}`.
+#[derive(Debug, Clone)]
+pub struct SlackVerifier {
+    pub base_url: String,
+}
+
+impl Default for SlackVerifier {
+    fn default() -> Self {
+        Self {
+            base_url: "https://slack.com".to_string(),
+        }
+    }
+}
+
+impl SecretVerifier for SlackVerifier {
+    fn name(&self) -> &'static str {
+        "slack"
+    }
+
+    fn rule_ids(&self) -> &'static [&'static str] {
+        &[
+            "slack-bot-token",
+            "slack-user-token",
+            "slack-app-token",
+            "slack-legacy-bot-token",
+            "slack-legacy-token",
+            "slack-legacy-workspace-token",
+        ]
+    }
+
+    fn verify<'a>(
+        &'a self,
+        fetcher: &'a Fetcher,
+        finding: &'a Finding,
+    ) -> BoxFuture<'a, AnyResult<Verification>> {
+        Box::pin(async move {
+            let rb = fetcher
+                .client()
+                .post(endpoint(&self.base_url, "/api/auth.test"))
+                .bearer_auth(&finding.secret);
+            let (status, body) = call(fetcher, rb).await?;
+            if !status.is_success() {
+                return Err(unexpected(status));
+            }
+            let v: Value = serde_json::from_str(&body).context("ответ auth.test не JSON")?;
+            if v.get("ok").and_then(Value::as_bool) == Some(true) {
+                let who = [json_str(&body, "team"), json_str(&body, "user")]
+                    .into_iter()
+                    .filter(|s| !s.is_empty())
+                    .collect::<Vec<_>>()
+                    .join("/");
+                Ok(Verification::new(VerifyStatus::Verified, who))
+            } else {
+                Ok(Verification::new(VerifyStatus::Rejected, json_str(&body, "error")))
+            }
+        })
+    }
+}
+
+/// Ключи Stripe: `GET /v1/balance`; 403 у restricted-ключа без прав на баланс —
+/// ключ всё равно рабочий.
+#[derive(Debug, Clone)]
+pub struct StripeVerifier {
+    pub base_url: String,
+}
+
+impl Default for StripeVerifier {
+    fn default() -> Self {
+        Self {
+            base_url: "https://api.stripe.com".to_string(),
+        }
+    }
+}
+
+impl SecretVerifier for StripeVerifier {
+    fn name(&self) -> &'static str {
+        "stripe"
+    }
+
+    fn rule_ids(&self) -> &'static [&'static str] {
+        &["stripe-access-token"]
+    }
+
+    fn verify<'a>(
+        &'a self,
+        fetcher: &'a Fetcher,
+        finding: &'a Finding,
+    ) -> BoxFuture<'a, AnyResult<Verification>> {
+        Box::pin(async move {
+            let rb = fetcher
+                .client()
+                .get(endpoint(&self.base_url, "/v1/balance"))
+                .basic_auth(&finding.secret, Some(""));
+            match call(fetcher, rb).await? {
+                (s, _) if s.is_success() => {
+                    let mode = if finding.secret.contains("_live_") { "live" } else { "test" };
+                    Ok(Verification::new(VerifyStatus::Verified, mode))
+                }
+                (StatusCode::FORBIDDEN, _) => {
+                    Ok(Verification::new(VerifyStatus::Verified, "restricted"))
+                }
+                (StatusCode::UNAUTHORIZED, _) => Ok(Verification::new(VerifyStatus::Rejected, "401")),
+                (s, _) => Err(unexpected(s)),
+            }
+        })
+    }
+}
+
+/// Ключи SendGrid: `GET /v3/scopes`.
+#[derive(Debug, Clone)]
+pub struct SendGridVerifier {
+    pub base_url: String,
+}
+
+impl Default for SendGridVerifier {
+    fn default() -> Self {
+        Self {
+            base_url: "https://api.sendgrid.com".to_string(),
+        }
+    }
+}
+
+impl SecretVerifier for SendGridVerifier {
+    fn name(&self) -> &'static str {
+        "sendgrid"
+    }
+
+    fn rule_ids(&self) -> &'static [&'static str] {
+        &["sendgrid-api-token"]
+    }
+
+    fn verify<'a>(
+        &'a self,
+        fetcher: &'a Fetcher,
+        finding: &'a Finding,
+    ) -> BoxFuture<'a, AnyResult<Verification>> {
+        Box::pin(async move {
+            let rb = fetcher
+                .client()
+                .get(endpoint(&self.base_url, "/v3/scopes"))
+                .bearer_auth(&finding.secret);
+            match call(fetcher, rb).await? {
+                (s, body) if s.is_success() => {
+                    let scopes = serde_json::from_str::<Value>(&body)
+                        .ok()
+                        .and_then(|v| v.get("scopes").and_then(Value::as_array).map(Vec::len))
+                        .unwrap_or(0);
+                    Ok(Verification::new(VerifyStatus::Verified, format!("scopes: {scopes}")))
+                }
+                (s @ (StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN), _) => {
+                    Ok(Verification::new(VerifyStatus::Rejected, s.as_u16().to_string()))
+                }
+                (s, _) => Err(unexpected(s)),
+            }
+        })
+    }
+}
+
+/// Итоги проверки.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct VerifyStats {
+    pub verified: usize,
+    pub rejected: usize,
+    pub errors: usize,
+}
+
+/// Набор проверяльщиков, выбор — по `rule_id` находки.
+#[derive(Debug, Clone, Default)]
+pub struct Verifiers {
+    list: Vec<Arc<dyn SecretVerifier>>,
+}
+
+impl Verifiers {
+    /// GitHub, Slack, Stripe и SendGrid с их настоящими API.
+    pub fn builtin() -> Self {
+        Self::default()
+            .with(GitHubVerifier::default())
+            .with(SlackVerifier::default())
+            .with(StripeVerifier::default())
+            .with(SendGridVerifier::default())
+    }
+
+    /// Добавить проверяльщика; тот, что с тем же именем, заменяется.
+    pub fn with(mut self, v: impl SecretVerifier + 'static) -> Self {
+        self.list.retain(|old| old.name() != v.name());
+        self.list.push(Arc::new(v));
+        self
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        self.list.iter().map(|v| v.name()).collect()
+    }
+
+    pub fn for_rule(&self, rule_id: &str) -> Option<&Arc<dyn SecretVerifier>> {
+        self.list.iter().find(|v| v.rule_ids().contains(&rule_id))
+    }
+
+    /// Проверить находки, для правил которых есть проверяльщик. Один и тот же
+    /// секрет уходит провайдеру один раз.
+    pub async fn verify_all(&self, fetcher: &Fetcher, findings: &mut [Finding]) -> VerifyStats {
+        let mut cache: HashMap<(&'static str, String), Verification> = HashMap::new();
+        let mut stats = VerifyStats::default();
+
+        for f in findings.iter_mut() {
+            let Some(v) = self.for_rule(&f.rule_id) else { continue };
+            let key = (v.name(), f.secret.clone());
+            let result = match cache.get(&key) {
+                Some(r) => r.clone(),
+                None => {
+                    let r = v.verify(fetcher, f).await.unwrap_or_else(|e| {
+                        Verification::new(VerifyStatus::Error, format!("{e:#}"))
+                    });
+                    cache.insert(key, r.clone());
+                    r
+                }
+            };
+            match result.status {
+                VerifyStatus::Verified => stats.verified += 1,
+                VerifyStatus::Rejected => stats.rejected += 1,
+                VerifyStatus::Error => stats.errors += 1,
+            }
+            f.verification = Some(result);
+        }
+        stats
+    }
+}
+
+/// Проверить находки готового скана: `findings.jsonl` и `findings.sarif`
+/// пересобираются, рабочие ключи дописываются в конец `sensitive_info.txt`.
+pub async fn verify_scan(paths: &Paths, fetcher: &Fetcher, verifiers: &Verifiers) -> AnyResult<VerifyStats> {
+    let mut findings = read_jsonl(&paths.findings_jsonl)?;
+    let stats = verifiers.verify_all(fetcher, &mut findings).await;
+
+    let mut w = JsonlWriter::create(&paths.findings_jsonl)?;
+    for f in &findings {
+        w.write(f)?;
+    }
+    w.flush()?;
+    write_sarif(&findings, &paths.findings_sarif)?;
+
+    let verified: Vec<&Finding> = findings
+        .iter()
+        .filter(|f| f.verification.as_ref().is_some_and(|v| v.status == VerifyStatus::Verified))
+        .collect();
+    if !verified.is_empty() {
+        let mut info = OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(&paths.sensitive_info_txt)
+            .with_context(|| format!("запись {}", paths.sensitive_info_txt.display()))?;
+        writeln!(info, "Рабочие ключи (--verify):")?;
+        for f in verified {
+            let detail = f.verification.as_ref().and_then(|v| v.detail.as_deref()).unwrap_or("");
+            writeln!(info, "  - [{}] {} | {} | {}", f.rule_id, f.secret, f.url, detail)?;
+        }
+    }
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server::{block_on, fetcher, serve, Reply};
+    use std::time::Duration;
+
+    fn finding(rule_id: &str, secret: &str) -> Finding {
+        Finding::new(rule_id, "", "https://ex.com/app.js", secret, 0, secret, secret)
+    }
+
+    async fn check(v: &dyn SecretVerifier, rule_id: &str, secret: &str) -> AnyResult<Verification> {
+        v.verify(&fetcher(), &finding(rule_id, secret)).await
+    }
+
+    fn assert_status(res: &AnyResult<Verification>, status: VerifyStatus, detail: &str) {
+        let v = res.as_ref().unwrap_or_else(|e| panic!("{e:#}"));
+        assert_eq!(v.status, status);
+        assert_eq!(v.detail.as_deref().unwrap_or(""), detail);
+    }
+
+    #[test]
+    fn github() {
+        block_on(async {
+            let (base, log) = serve(|req| {
+                assert_eq!((req.method.as_str(), req.path()), ("GET", "/user"));
+                match req.header("Authorization").unwrap_or("") {
+                    "token ghp_good" => Reply::new(200, r#"{"login":"octocat"}"#),
+                    "token ghp_bad" => Reply::new(401, r#"{"message":"Bad credentials"}"#),
+                    "token ghp_slow" => Reply::new(200, "{}").delayed(Duration::from_secs(3)),
+                    _ => Reply::new(500, ""),
+                }
+            })
+            .await;
+            let v = GitHubVerifier { base_url: base };
+
+            assert_status(&check(&v, "github-pat", "ghp_good").await, VerifyStatus::Verified, "octocat");
+            assert_status(&check(&v, "github-pat", "ghp_bad").await, VerifyStatus::Rejected, "401");
+            assert!(check(&v, "github-pat", "ghp_boom").await.is_err());
+            assert!(check(&v, "github-pat", "ghp_slow").await.is_err());
+            assert_eq!(log.lock().unwrap().len(), 4);
+        });
+    }
+
+    #[test]
+    fn slack() {
+        block_on(async {
+            let (base, _) = serve(|req| {
+                assert_eq!((req.method.as_str(), req.path()), ("POST", "/api/auth.test"));
+                match req.header("Authorization").unwrap_or("") {
+                    "Bearer xoxb-good" => Reply::new(200, r#"{"ok":true,"team":"acme","user":"bot"}"#),
+                    "Bearer xoxb-bad" => Reply::new(200, r#"{"ok":false,"error":"invalid_auth"}"#),
+                    "Bearer xoxb-html" => Reply::new(200, "<html>"),
+                    _ => Reply::new(500, ""),
+                }
+            })
+            .await;
+            let v = SlackVerifier { base_url: base };
+
+            assert_status(&check(&v, "slack-bot-token", "xoxb-good").await, VerifyStatus::Verified, "acme/bot");
+            assert_status(
+                &check(&v, "slack-bot-token", "xoxb-bad").await,
+                VerifyStatus::Rejected,
+                "invalid_auth",
+            );
+            assert!(check(&v, "slack-bot-token", "xoxb-html").await.is_err());
+            assert!(check(&v, "slack-bot-token", "xoxb-boom").await.is_err());
+        });
+    }
+
+    #[test]
+    fn stripe() {
+        block_on(async {
+            let (base, _) = serve(|req| {
+                assert_eq!(req.path(), "/v1/balance");
+                match req.header("Authorization").unwrap_or("") {
+                    "Basic c2tfbGl2ZV9nb29kOg==" => Reply::new(200, r#"{"object":"balance"}"#),
+                    "Basic cmtfbGl2ZV9yZXN0cmljdGVkOg==" => Reply::new(403, "{}"),
+                    "Basic c2tfbGl2ZV9iYWQ6" => Reply::new(401, "{}"),
+                    _ => Reply::new(500, ""),
+                }
+            })
+            .await;
+            let v = StripeVerifier { base_url: base };
+            let rule = "stripe-access-token";
+
+            assert_status(&check(&v, rule, "sk_live_good").await, VerifyStatus::Verified, "live");
+            assert_status(&check(&v, rule, "rk_live_restricted").await, VerifyStatus::Verified, "restricted");
+            assert_status(&check(&v, rule, "sk_live_bad").await, VerifyStatus::Rejected, "401");
+            assert!(check(&v, rule, "sk_test_boom").await.is_err());
+        });
+    }
+
+    #[test]
+    fn sendgrid() {
+        block_on(async {
+            let (base, _) = serve(|req| {
+                assert_eq!(req.path(), "/v3/scopes");
+                match req.header("Authorization").unwrap_or("") {
+                    "Bearer SG.good" => Reply::new(200, r#"{"scopes":["mail.send","alerts.read"]}"#),
+                    "Bearer SG.bad" => Reply::new(401, "{}"),
+                    "Bearer SG.forbidden" => Reply::new(403, "{}"),
+                    _ => Reply::new(500, ""),
+                }
+            })
+            .await;
+            let v = SendGridVerifier { base_url: base };
+            let rule = "sendgrid-api-token";
+
+            assert_status(&check(&v, rule, "SG.good").await, VerifyStatus::Verified, "scopes: 2");
+            assert_status(&check(&v, rule, "SG.bad").await, VerifyStatus::Rejected, "401");
+            assert_status(&check(&v, rule, "SG.forbidden").await, VerifyStatus::Rejected, "403");
+            assert!(check(&v, rule, "SG.boom").await.is_err());
+        });
+    }
+
+    #[test]
+    fn verify_all_caches_and_counts() {
+        block_on(async {
+            let (base, log) = serve(|req| match req.header("Authorization").unwrap_or("") {
+                "token ghp_good" => Reply::new(200, r#"{"login":"octocat"}"#),
+                "token ghp_bad" => Reply::new(401, "{}"),
+                _ => Reply::new(500, ""),
+            })
+            .await;
+            let verifiers = Verifiers::builtin().with(GitHubVerifier { base_url: base });
+            let mut findings = vec![
+                finding("github-pat", "ghp_good"),
+                finding("github-pat", "ghp_good"),
+                finding("github-oauth", "ghp_bad"),
+                finding("github-pat", "ghp_boom"),
+                finding("generic-api-key", "whatever"),
+            ];
+
+            let stats = verifiers.verify_all(&fetcher(), &mut findings).await;
+
+            assert_eq!((stats.verified, stats.rejected, stats.errors), (2, 1, 1));
+            assert_eq!(log.lock().unwrap().len(), 3);
+            assert_eq!(findings[3].verification.as_ref().map(|v| v.status), Some(VerifyStatus::Error));
+            assert!(findings[4].verification.is_none());
+        });
+    }
+}